@@ -0,0 +1,35 @@
+//! Precompute which seconds of the day are significant for each preset rule set, as a bitset
+//! embedded by `src/table.rs`.
+
+use std::{env, fs, path::Path};
+
+#[allow(dead_code)]
+#[path = "src/rules.rs"]
+mod rules;
+
+use chrono::NaiveTime;
+use rules::{SignificanceRule, BUILTIN_RULES, DEFAULT};
+
+/// Bit `n % 8` of byte `n / 8` is set if second `n` of the day is significant.
+fn encode(rules: &[&dyn SignificanceRule]) -> Vec<u8> {
+    let mut table = vec![0u8; 86_400 / 8];
+    for secs in 0..86_400u32 {
+        let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap();
+        let digits = rules::digits(&time);
+        if rules.iter().any(|rule| rule.matches(&digits)) {
+            table[secs as usize / 8] |= 1 << (secs % 8);
+        }
+    }
+    table
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/rules.rs");
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set by cargo");
+    let presets = [("default", DEFAULT), ("all", BUILTIN_RULES)];
+    for (name, rules) in presets {
+        let path = Path::new(&out_dir).join(format!("{name}.bin"));
+        fs::write(path, encode(rules)).expect("Unable to write significance table");
+    }
+}
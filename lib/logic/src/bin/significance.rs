@@ -0,0 +1,380 @@
+//! Inspect significance rules from a workstation.
+//!
+//! ```text
+//! significance [OPTIONS] list
+//! significance [OPTIONS] explain HH:MM:SS
+//! significance [OPTIONS] export csv|json
+//! significance [OPTIONS] counts
+//! ```
+//!
+//! Options choose the rules (`--rules`, `--pattern`, `--threshold`) and how the clock shows the
+//! time (`--12h`, `--minutes`); run without arguments for details.
+//!
+//! `export csv` with the default options regenerates `significance.csv`.
+use std::io::{self, BufWriter, Write};
+use std::{env, fmt, process::ExitCode};
+
+use chrono::NaiveTime;
+use logic::format::{HourFormat, Resolution};
+use logic::maths;
+use logic::pattern::{Pattern, PatternError};
+use logic::registry::{RuleSet, UnknownRule};
+use logic::rules::{Digits, Significance, BUILTIN, MATCH, MINUTE};
+use logic::table::SECONDS_PER_DAY;
+
+const USAGE: &str = "\
+Usage: significance [OPTIONS] COMMAND
+
+Commands:
+  list              Every significant time of the day
+  explain HH:MM:SS  Why a time is or isn't significant
+  export csv|json   Significance of every second of the day
+  counts            How often each rule matches in a day
+
+Options:
+  --rules a,b,...   Rules to use, in priority order (default: the default rule set)
+  --pattern P       Also match a user-defined pattern such as AB:BA:* (repeatable)
+  --12h             Read times as shown on a 12-hour clock
+  --minutes         Ignore seconds, as on a clock without them (default rules: the minute set)
+  --threshold N     Also accept near misses scoring at least N (default: 100)";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    List,
+    Explain(NaiveTime),
+    Export(Format),
+    Counts,
+}
+
+#[derive(Debug, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    rules: Option<Vec<String>>,
+    patterns: Vec<String>,
+    hour_format: HourFormat,
+    resolution: Resolution,
+    threshold: u8,
+    command: Command,
+}
+
+#[derive(Debug)]
+enum Error {
+    Usage(String),
+    Rule(UnknownRule),
+    Pattern(String, PatternError),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{msg}"),
+            Error::Rule(err) => {
+                let maths = maths::RULES.iter().map(|rule| rule.name());
+                let known: Vec<&str> = BUILTIN.iter().copied().chain(maths).collect();
+                write!(f, "{err} (known rules: {})", known.join(", "))
+            }
+            Error::Pattern(source, err) => write!(f, "Invalid pattern '{source}': {err}"),
+            Error::Io(err) => write!(f, "Unable to write output: {err}"),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<UnknownRule> for Error {
+    fn from(err: UnknownRule) -> Error {
+        Error::Rule(err)
+    }
+}
+
+fn usage(msg: impl Into<String>) -> Error {
+    Error::Usage(msg.into())
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, Error> {
+        let mut args = args.into_iter();
+        let mut rules = None;
+        let mut patterns = vec![];
+        let mut hour_format = HourFormat::TwentyFour;
+        let mut resolution = Resolution::Seconds;
+        let mut threshold = MATCH;
+        let mut command = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--rules" => {
+                    let names = args.next().ok_or_else(|| usage("--rules needs a value"))?;
+                    rules = Some(names.split(',').map(String::from).collect());
+                }
+                "--pattern" => {
+                    patterns.push(
+                        args.next()
+                            .ok_or_else(|| usage("--pattern needs a value"))?,
+                    );
+                }
+                "--12h" => hour_format = HourFormat::Twelve,
+                "--minutes" => resolution = Resolution::Minutes,
+                "--threshold" => {
+                    threshold = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| usage("--threshold needs a number from 0 to 255"))?;
+                }
+                "list" => command = Some(Command::List),
+                "counts" => command = Some(Command::Counts),
+                "explain" => {
+                    let time = args.next().ok_or_else(|| usage("explain needs a time"))?;
+                    let time = NaiveTime::parse_from_str(&time, "%H:%M:%S")
+                        .map_err(|_| usage(format!("'{time}' is not a time like 12:34:56")))?;
+                    command = Some(Command::Explain(time));
+                }
+                "export" => {
+                    let format = match args.next().as_deref() {
+                        Some("csv") => Format::Csv,
+                        Some("json") => Format::Json,
+                        _ => return Err(usage("export needs a format: csv or json")),
+                    };
+                    command = Some(Command::Export(format));
+                }
+                other => return Err(usage(format!("Unexpected argument '{other}'"))),
+            }
+        }
+        let command = command.ok_or_else(|| usage("No command given"))?;
+        Ok(Args {
+            rules,
+            patterns,
+            hour_format,
+            resolution,
+            threshold,
+            command,
+        })
+    }
+
+    fn rule_set(&self) -> Result<RuleSet, Error> {
+        let mut rules = match &self.rules {
+            Some(names) => RuleSet::from_names(names)?,
+            None if self.resolution == Resolution::Minutes => RuleSet::preset(MINUTE),
+            None => RuleSet::default(),
+        };
+        for source in &self.patterns {
+            let pattern =
+                Pattern::parse(source).map_err(|err| Error::Pattern(source.clone(), err))?;
+            rules.push(Box::new(pattern));
+        }
+        Ok(rules)
+    }
+
+    /// Digits of `time` as seen by the rules.
+    fn digits(&self, time: &NaiveTime) -> Digits {
+        self.resolution.digits(&self.hour_format.displayed(time))
+    }
+
+    /// Every second, or minute, of the day.
+    fn day(&self) -> impl Iterator<Item = NaiveTime> {
+        let step = match self.resolution {
+            Resolution::Seconds => 1,
+            Resolution::Minutes => 60,
+        };
+        (0..SECONDS_PER_DAY as u32)
+            .step_by(step)
+            .map(|secs| NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap())
+    }
+}
+
+fn explain<'a>(args: &Args, rules: &'a RuleSet, time: &NaiveTime) -> Option<Significance<'a>> {
+    rules.explain_above(&args.digits(time), args.threshold)
+}
+
+/// Mark the digits of `HH:MM:SS` taking part in a pattern.
+fn underline(significance: &Significance) -> String {
+    (0..8)
+        .map(|col| match col % 3 {
+            2 => ' ',
+            offset if significance.includes(col / 3 * 2 + offset) => '^',
+            _ => ' ',
+        })
+        .collect::<String>()
+        .trim_end()
+        .into()
+}
+
+fn json_string(value: Option<&str>) -> String {
+    // Rule names and labels are plain ASCII without quotes or backslashes.
+    value.map_or("null".into(), |value| format!("\"{value}\""))
+}
+
+fn run(args: &Args, out: &mut impl Write) -> Result<(), Error> {
+    let rules = args.rule_set()?;
+    match &args.command {
+        Command::List => {
+            for time in args.day() {
+                if let Some(significance) = explain(args, &rules, &time) {
+                    writeln!(out, "{} {}", time.format("%H:%M:%S"), significance.rule)?;
+                }
+            }
+        }
+        Command::Explain(time) => {
+            let shown = args.hour_format.displayed(time);
+            match explain(args, &rules, time) {
+                Some(significance) => {
+                    writeln!(out, "{}: {}", shown.format("%H:%M:%S"), significance.label)?;
+                    writeln!(out, "{}", underline(&significance))?;
+                }
+                None => writeln!(out, "{}: not significant", shown.format("%H:%M:%S"))?,
+            }
+            let digits = args.digits(time);
+            for rule in rules.enabled() {
+                writeln!(out, "  {:<12}{:>4}", rule.name(), rule.score(&digits))?;
+            }
+        }
+        Command::Export(Format::Csv) => {
+            for time in args.day() {
+                let significant = explain(args, &rules, &time).is_some();
+                writeln!(out, "{},{significant}", time.format("%H:%M:%S"))?;
+            }
+        }
+        Command::Export(Format::Json) => {
+            write!(out, "[")?;
+            for (i, time) in args.day().enumerate() {
+                let significance = explain(args, &rules, &time);
+                write!(
+                    out,
+                    "{}\n  {{\"time\": \"{}\", \"rule\": {}, \"label\": {}, \"score\": {}}}",
+                    if i == 0 { "" } else { "," },
+                    time.format("%H:%M:%S"),
+                    json_string(significance.map(|s| s.rule)),
+                    json_string(significance.map(|s| s.label)),
+                    significance.map_or(0, |s| s.score),
+                )?;
+            }
+            writeln!(out, "\n]")?;
+        }
+        Command::Counts => {
+            let mut total = 0;
+            let mut counts: Vec<(&str, usize)> =
+                rules.enabled().map(|rule| (rule.name(), 0)).collect();
+            for time in args.day() {
+                let digits = args.digits(&time);
+                for ((_, count), rule) in counts.iter_mut().zip(rules.enabled()) {
+                    *count += rule.matches(&digits) as usize;
+                }
+                total += rules.matches(&digits) as usize;
+            }
+            for (name, count) in counts {
+                writeln!(out, "{name:<12}{count:>6}")?;
+            }
+            writeln!(out, "{:<12}{total:>6}", "any")?;
+        }
+    }
+    Ok(out.flush()?)
+}
+
+fn main() -> ExitCode {
+    let result = Args::parse(env::args().skip(1))
+        .and_then(|args| run(&args, &mut BufWriter::new(io::stdout().lock())));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // Piped into `head` or similar.
+        Err(Error::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err @ Error::Usage(_)) => {
+            eprintln!("{err}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logic::rules::FlashStyle;
+
+    fn parse(args: &[&str]) -> Result<Args, Error> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_may_precede_command() {
+        let args = parse(&["--rules", "run,mirror", "--12h", "explain", "13:23:45"]).unwrap();
+        assert_eq!(args.rules, Some(vec!["run".into(), "mirror".into()]));
+        assert_eq!(args.hour_format, HourFormat::Twelve);
+        assert_eq!(
+            args.command,
+            Command::Explain(NaiveTime::from_hms_opt(13, 23, 45).unwrap())
+        );
+    }
+
+    #[test]
+    fn bad_arguments_are_reported() {
+        assert!(matches!(parse(&[]), Err(Error::Usage(_))));
+        assert!(matches!(parse(&["export", "xml"]), Err(Error::Usage(_))));
+        assert!(matches!(
+            parse(&["explain", "25:00:00"]),
+            Err(Error::Usage(_))
+        ));
+        let args = parse(&["--rules", "run,nope", "list"]).unwrap();
+        assert!(matches!(args.rule_set(), Err(Error::Rule(UnknownRule(name))) if name == "nope"));
+        let args = parse(&["--pattern", "AB:xx", "list"]).unwrap();
+        assert!(matches!(args.rule_set(), Err(Error::Pattern(..))));
+    }
+
+    #[test]
+    fn underline_marks_participating_digits() {
+        let significance = Significance {
+            rule: "doubles",
+            label: "pairs",
+            digits: 0b11_0011,
+            score: MATCH,
+            flash: FlashStyle::Double,
+        };
+        assert_eq!(underline(&significance), "^^    ^^");
+    }
+
+    /// What `run` prints for `args`.
+    fn output(args: &[&str]) -> String {
+        let mut out = vec![];
+        run(&parse(args).unwrap(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn explain_underlines_the_digits_in_the_pattern() {
+        let out = output(&[
+            "--rules",
+            "doubles",
+            "--threshold",
+            "50",
+            "explain",
+            "11:23:44",
+        ]);
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("11:23:44: pairs"));
+        assert_eq!(lines.next(), Some("^^    ^^"));
+
+        // User patterns only mark the digits they say something about.
+        let out = output(&[
+            "--rules",
+            "run",
+            "--pattern",
+            "AB:BA:*",
+            "explain",
+            "12:21:59",
+        ]);
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("12:21:59: AB:BA:*"));
+        assert_eq!(lines.next(), Some("^^ ^^"));
+    }
+}
@@ -0,0 +1,255 @@
+//! Patterns spanning the date as well as the time, like 11/11 11:11.
+use chrono::{Datelike, Timelike};
+
+use crate::format::HourFormat;
+use crate::rules::{all_digits, digits, Digits, FlashStyle, Significance, MATCH};
+
+/// Order of day and month when showing the date.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DateFormat {
+    #[default]
+    Dmy,
+    Mdy,
+}
+
+impl DateFormat {
+    /// `strftime` format for the day and month.
+    pub fn day_month(&self) -> &'static str {
+        match self {
+            DateFormat::Dmy => "%d/%m",
+            DateFormat::Mdy => "%m/%d",
+        }
+    }
+
+    /// The day and month digits, in the order shown.
+    pub fn digits(&self, date: &impl Datelike) -> Digits {
+        let (first, second) = match self {
+            DateFormat::Dmy => (date.day(), date.month()),
+            DateFormat::Mdy => (date.month(), date.day()),
+        };
+        Digits::from_pairs(&[first, second])
+    }
+}
+
+/// The date and time as shown on the clock.
+pub struct Shown {
+    pub day: u32,
+    pub month: u32,
+    /// Day and month digits, in the order shown.
+    pub date: Digits,
+    /// Time digits (`HHMMSS`).
+    pub time: Digits,
+}
+
+impl Shown {
+    pub fn new<T: Datelike + Timelike>(
+        time: &T,
+        date_format: DateFormat,
+        hour_format: HourFormat,
+    ) -> Shown {
+        Shown {
+            day: time.day(),
+            month: time.month(),
+            date: date_format.digits(time),
+            time: digits(&hour_format.displayed(time)),
+        }
+    }
+}
+
+/// A pattern spanning the date and time.
+pub trait DateRule: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Human-readable description of the pattern.
+    fn label(&self) -> &str {
+        self.name()
+    }
+
+    fn matches(&self, shown: &Shown) -> bool;
+
+    /// How the lamp flashes when this rule fires.  Date patterns come round at most once a
+    /// year, so by default they get the most flashes.
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Triple
+    }
+
+    /// Bitmask of the digits taking part when matched: the four date digits, then the time.
+    fn participants(&self, shown: &Shown) -> u16 {
+        all_digits(&shown.date) | all_digits(&shown.time) << shown.date.len()
+    }
+}
+
+/// The date repeated as hours and minutes: 12/03 12:03.
+pub struct DateRepeat;
+
+impl DateRule for DateRepeat {
+    fn name(&self) -> &str {
+        "date-repeat"
+    }
+
+    fn label(&self) -> &str {
+        "date time"
+    }
+
+    fn matches(&self, shown: &Shown) -> bool {
+        *shown.date == shown.time[..4]
+    }
+
+    fn participants(&self, shown: &Shown) -> u16 {
+        all_digits(&shown.time[..4]) << 4 | all_digits(&shown.date)
+    }
+}
+
+/// The date reversed as hours and minutes: 21/02 20:12.
+pub struct DateMirror;
+
+impl DateRule for DateMirror {
+    fn name(&self) -> &str {
+        "date-mirror"
+    }
+
+    fn label(&self) -> &str {
+        "date mirror"
+    }
+
+    fn matches(&self, shown: &Shown) -> bool {
+        shown.date.iter().eq(shown.time[..4].iter().rev())
+    }
+
+    fn participants(&self, shown: &Shown) -> u16 {
+        DateRepeat.participants(shown)
+    }
+}
+
+/// Date, hours and minutes all one digit: 11/11 11:11.
+pub struct Repdigit;
+
+impl DateRule for Repdigit {
+    fn name(&self) -> &str {
+        "repdigit"
+    }
+
+    fn matches(&self, shown: &Shown) -> bool {
+        shown
+            .date
+            .iter()
+            .chain(&shown.time[..4])
+            .all(|digit| *digit == shown.date[0])
+    }
+
+    fn participants(&self, shown: &Shown) -> u16 {
+        DateRepeat.participants(shown)
+    }
+}
+
+/// 3/14 1:59:26, the first digits of pi.
+pub struct PiDay;
+
+impl DateRule for PiDay {
+    fn name(&self) -> &str {
+        "pi"
+    }
+
+    fn label(&self) -> &str {
+        "pi day"
+    }
+
+    fn matches(&self, shown: &Shown) -> bool {
+        (shown.month, shown.day) == (3, 14) && shown.time == Digits::from_hms(1, 59, 26)
+    }
+}
+
+/// Every date rule, in priority order.
+pub const RULES: &[&dyn DateRule] = &[&Repdigit, &PiDay, &DateRepeat, &DateMirror];
+
+/// The first date rule matching `time` as shown with the given formats, if any.
+pub fn explain<T: Datelike + Timelike>(
+    time: &T,
+    date_format: DateFormat,
+    hour_format: HourFormat,
+) -> Option<Significance<'static>> {
+    let shown = Shown::new(time, date_format, hour_format);
+    RULES
+        .iter()
+        .find(|rule| rule.matches(&shown))
+        .map(|rule| Significance {
+            rule: rule.name(),
+            label: rule.label(),
+            digits: rule.participants(&shown),
+            score: MATCH,
+            flash: rule.flash(),
+        })
+}
+
+pub fn is_significant<T: Datelike + Timelike>(
+    time: &T,
+    date_format: DateFormat,
+    hour_format: HourFormat,
+) -> bool {
+    explain(time, date_format, hour_format).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::date::{explain, is_significant, DateFormat};
+    use crate::format::HourFormat;
+    use chrono::prelude::*;
+
+    fn rule(time: DateTime<Local>, format: DateFormat) -> Option<&'static str> {
+        explain(&time, format, HourFormat::TwentyFour).map(|significance| significance.rule)
+    }
+
+    #[test]
+    fn eleven_eleven_is_significant() {
+        let time = Local.with_ymd_and_hms(2024, 11, 11, 11, 11, 0).unwrap();
+        assert_eq!(rule(time, DateFormat::Dmy), Some("repdigit"));
+    }
+
+    #[test]
+    fn date_repeat_follows_format() {
+        let time = Local.with_ymd_and_hms(2024, 3, 12, 12, 3, 0).unwrap();
+        assert_eq!(rule(time, DateFormat::Dmy), Some("date-repeat"));
+        assert_eq!(rule(time, DateFormat::Mdy), None);
+        let time = Local.with_ymd_and_hms(2024, 3, 12, 3, 12, 0).unwrap();
+        assert_eq!(rule(time, DateFormat::Mdy), Some("date-repeat"));
+    }
+
+    #[test]
+    fn date_mirror_is_significant() {
+        let time = Local.with_ymd_and_hms(2024, 2, 21, 20, 12, 45).unwrap();
+        assert_eq!(rule(time, DateFormat::Dmy), Some("date-mirror"));
+    }
+
+    #[test]
+    fn pi_day_in_either_format() {
+        let time = Local.with_ymd_and_hms(2024, 3, 14, 1, 59, 26).unwrap();
+        assert_eq!(rule(time, DateFormat::Mdy), Some("pi"));
+        assert_eq!(rule(time, DateFormat::Dmy), Some("pi"));
+        let afternoon = Local.with_ymd_and_hms(2024, 3, 14, 13, 59, 26).unwrap();
+        assert!(!is_significant(
+            &afternoon,
+            DateFormat::Mdy,
+            HourFormat::TwentyFour
+        ));
+        assert!(is_significant(
+            &afternoon,
+            DateFormat::Mdy,
+            HourFormat::Twelve
+        ));
+    }
+
+    #[test]
+    fn pi_rule_names_date_and_time_digits() {
+        let time = Local.with_ymd_and_hms(2024, 3, 14, 1, 59, 26).unwrap();
+        let significance = explain(&time, DateFormat::Mdy, HourFormat::TwentyFour).unwrap();
+        assert_eq!(significance.digits, 0b11_1111_1111);
+    }
+
+    #[test]
+    fn ordinary_dates_are_not_significant() {
+        let time = Local.with_ymd_and_hms(2024, 6, 27, 10, 27, 43).unwrap();
+        assert_eq!(rule(time, DateFormat::Dmy), None);
+        assert_eq!(rule(time, DateFormat::Mdy), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! How the clock displays the time.
+use chrono::{NaiveTime, Timelike};
+
+use crate::rules::{digits, minute_digits, Digits};
+
+/// Whether hours run 0–23 or 1–12.  Significance rules see the hour as displayed, so on a 12-hour
+/// clock 13:23:45 is read as 01:23:45.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HourFormat {
+    #[default]
+    TwentyFour,
+    Twelve,
+}
+
+impl HourFormat {
+    /// The hour shown on the clock for `hour` (0–23).
+    pub fn hour(&self, hour: u32) -> u32 {
+        match (self, hour % 12) {
+            (HourFormat::TwentyFour, _) => hour,
+            (HourFormat::Twelve, 0) => 12,
+            (HourFormat::Twelve, hour) => hour,
+        }
+    }
+
+    /// `time` with the hour as shown on the clock.
+    pub fn displayed<T: Timelike>(&self, time: &T) -> NaiveTime {
+        NaiveTime::from_hms_opt(self.hour(time.hour()), time.minute(), time.second())
+            .expect("Displayed hour out of range")
+    }
+
+    /// `strftime` format for hours and minutes.
+    pub fn hour_minute(&self) -> &'static str {
+        match self {
+            HourFormat::TwentyFour => "%H:%M",
+            HourFormat::Twelve => "%I:%M",
+        }
+    }
+}
+
+/// Whether significance looks at seconds, or only hours and minutes for clocks without seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Resolution {
+    #[default]
+    Seconds,
+    Minutes,
+}
+
+impl Resolution {
+    /// The digits of `time` which significance rules see.
+    pub fn digits<T: Timelike>(&self, time: &T) -> Digits {
+        match self {
+            Resolution::Seconds => digits(time),
+            Resolution::Minutes => minute_digits(time),
+        }
+    }
+
+    /// The start of the second or minute containing `time`.  Everything within it is the same
+    /// significant moment.
+    pub fn moment<T: Timelike>(&self, time: &T) -> T {
+        let time = time.with_nanosecond(0).expect("Zero nanoseconds are valid");
+        match self {
+            Resolution::Seconds => time,
+            Resolution::Minutes => time.with_second(0).expect("Zero seconds are valid"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{HourFormat, Resolution};
+    use chrono::{NaiveTime, Timelike};
+
+    #[test]
+    fn twelve_hour_clock_has_no_zero() {
+        let hours: Vec<u32> = (0..24).map(|h| HourFormat::Twelve.hour(h)).collect();
+        assert_eq!(hours[0], 12);
+        assert_eq!(hours[12], 12);
+        assert_eq!(hours[13], 1);
+        assert!(hours.iter().all(|h| (1..=12).contains(h)));
+    }
+
+    #[test]
+    fn twenty_four_hour_clock_is_unchanged() {
+        let time = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
+        assert_eq!(HourFormat::TwentyFour.displayed(&time), time);
+    }
+
+    #[test]
+    fn displayed_time_keeps_minutes_and_seconds() {
+        let time = NaiveTime::from_hms_opt(0, 34, 56).unwrap();
+        let displayed = HourFormat::Twelve.displayed(&time);
+        assert_eq!(
+            (displayed.hour(), displayed.minute(), displayed.second()),
+            (12, 34, 56)
+        );
+    }
+
+    #[test]
+    fn minutes_start_at_zero_seconds() {
+        let time = NaiveTime::from_hms_milli_opt(12, 34, 56, 789).unwrap();
+        assert_eq!(
+            Resolution::Minutes.moment(&time),
+            NaiveTime::from_hms_opt(12, 34, 0).unwrap()
+        );
+        assert_eq!(
+            Resolution::Seconds.moment(&time),
+            NaiveTime::from_hms_opt(12, 34, 56).unwrap()
+        );
+        assert_eq!(*Resolution::Minutes.digits(&time), [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,149 @@
+//! A record of the significant moments the clock has seen.
+use alloc::{collections::BTreeMap, collections::VecDeque, string::String};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// A significant moment, and the rule which made it so.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Moment {
+    pub time: NaiveDateTime,
+    pub rule: String,
+}
+
+/// Number of moments kept by default.
+pub const CAPACITY: usize = 512;
+
+/// Number of days for which [`History::by_day`] keeps counts.
+pub const DAYS: usize = 31;
+
+/// The most recent significant moments, plus running counts which outlive them.
+///
+/// Moments are kept in a ring buffer, so only the last `capacity` are available; counts per rule
+/// cover everything ever recorded, and counts per day cover the last [`DAYS`] days.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct History {
+    capacity: usize,
+    moments: VecDeque<Moment>,
+    rules: BTreeMap<String, u32>,
+    days: BTreeMap<NaiveDate, u32>,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::with_capacity(CAPACITY)
+    }
+}
+
+impl History {
+    pub fn with_capacity(capacity: usize) -> History {
+        History {
+            capacity,
+            moments: VecDeque::with_capacity(capacity),
+            rules: BTreeMap::new(),
+            days: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, time: NaiveDateTime, rule: &str) {
+        while self.moments.len() >= self.capacity.max(1) {
+            self.moments.pop_front();
+        }
+        self.moments.push_back(Moment {
+            time,
+            rule: rule.into(),
+        });
+        *self.rules.entry(rule.into()).or_default() += 1;
+        *self.days.entry(time.date()).or_default() += 1;
+        while self.days.len() > DAYS {
+            self.days.pop_first();
+        }
+    }
+
+    /// Recorded moments, oldest first.
+    pub fn moments(&self) -> impl DoubleEndedIterator<Item = &Moment> {
+        self.moments.iter()
+    }
+
+    /// How many times each rule has fired.
+    pub fn by_rule(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.rules
+            .iter()
+            .map(|(rule, count)| (rule.as_str(), *count))
+    }
+
+    /// How many significant moments there were on each recent day, oldest first.
+    pub fn by_day(&self) -> impl Iterator<Item = (NaiveDate, u32)> + '_ {
+        self.days.iter().map(|(day, count)| (*day, *count))
+    }
+
+    /// How many significant moments there were on `day`.
+    pub fn count_on(&self, day: NaiveDate) -> u32 {
+        self.days.get(&day).copied().unwrap_or(0)
+    }
+
+    pub fn clear(&mut self) {
+        *self = History::with_capacity(self.capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{History, DAYS};
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+    fn at(day: u32, h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(h, m, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn oldest_moments_are_dropped() {
+        let mut history = History::with_capacity(2);
+        history.record(at(1, 12, 34, 56), "run");
+        history.record(at(1, 13, 13, 13), "all-equal");
+        history.record(at(1, 13, 22, 31), "mirror");
+        let rules: Vec<&str> = history.moments().map(|m| m.rule.as_str()).collect();
+        assert_eq!(rules, vec!["all-equal", "mirror"]);
+    }
+
+    #[test]
+    fn counts_outlive_moments() {
+        let mut history = History::with_capacity(1);
+        history.record(at(1, 11, 11, 11), "all-equal");
+        history.record(at(1, 12, 12, 12), "all-equal");
+        history.record(at(2, 12, 34, 56), "run");
+        let rules: Vec<(&str, u32)> = history.by_rule().collect();
+        assert_eq!(rules, vec![("all-equal", 2), ("run", 1)]);
+        assert_eq!(history.count_on(at(1, 0, 0, 0).date()), 2);
+        assert_eq!(history.count_on(at(2, 0, 0, 0).date()), 1);
+        assert_eq!(history.count_on(at(3, 0, 0, 0).date()), 0);
+    }
+
+    #[test]
+    fn only_recent_days_are_counted() {
+        let mut history = History::default();
+        let start = at(1, 12, 34, 56);
+        for day in 0..DAYS as i64 + 5 {
+            history.record(start + Duration::days(day), "run");
+        }
+        let days: Vec<_> = history.by_day().collect();
+        assert_eq!(days.len(), DAYS);
+        assert_eq!(days[0], ((start + Duration::days(5)).date(), 1));
+    }
+
+    #[test]
+    fn clearing_keeps_capacity() {
+        let mut history = History::with_capacity(3);
+        history.record(at(1, 12, 34, 56), "run");
+        history.clear();
+        assert_eq!(history, History::with_capacity(3));
+    }
+}
@@ -1 +1,25 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod date;
+pub mod format;
+#[cfg(feature = "alloc")]
+pub mod history;
+pub mod maths;
+#[cfg(feature = "alloc")]
+pub mod pattern;
+#[cfg(feature = "alloc")]
+pub mod registry;
+pub mod rules;
+#[cfg(feature = "std")]
+pub mod schedule;
+#[cfg(feature = "alloc")]
+pub mod search;
 pub mod significance;
+#[cfg(feature = "std")]
+pub mod sun;
+pub mod table;
+#[cfg(feature = "alloc")]
+pub mod tracker;
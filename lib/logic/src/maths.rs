@@ -0,0 +1,274 @@
+//! Rules reading the time as a number, for clocks in maths classrooms.
+//!
+//! Most rules read the displayed digits as one decimal number, so 12:34:56 is 123456, or 1234 if
+//! only hours and minutes are given.  [`Milestone`] instead counts the seconds (or minutes) since
+//! midnight.
+//!
+//! These fire far more often than the digit patterns (there are thousands of primes in a day), so
+//! they all get a single flash.
+use chrono::Timelike;
+
+use crate::rules::{digits, FlashStyle, Significance, SignificanceRule};
+
+/// The digits read as one decimal number.
+pub fn number(digits: &[u8]) -> u32 {
+    digits
+        .iter()
+        .fold(0, |number, &digit| number * 10 + digit as u32)
+}
+
+/// Seconds since midnight for `HHMMSS`, or minutes for `HHMM`.
+pub fn elapsed(digits: &[u8]) -> u32 {
+    digits
+        .chunks(2)
+        .fold(0, |elapsed, pair| elapsed * 60 + number(pair))
+}
+
+/// Largest `root` with `root * root <= n`.
+fn isqrt(n: u32) -> u32 {
+    let mut root = 0u32;
+    let mut bit = 1 << 15;
+    while bit > 0 {
+        let candidate = root | bit;
+        if candidate * candidate <= n {
+            root = candidate;
+        }
+        bit >>= 1;
+    }
+    root
+}
+
+/// 12:34:57 (123457).
+pub struct Prime;
+
+impl SignificanceRule for Prime {
+    fn name(&self) -> &str {
+        "prime"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        let n = number(digits);
+        n >= 2 && (2..=isqrt(n)).all(|divisor| n % divisor != 0)
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Single
+    }
+}
+
+/// 10:24:00 (320²).
+pub struct Square;
+
+impl SignificanceRule for Square {
+    fn name(&self) -> &str {
+        "square"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        let n = number(digits);
+        isqrt(n) * isqrt(n) == n
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Single
+    }
+}
+
+/// 01:09:46 (10946).
+pub struct Fibonacci;
+
+impl SignificanceRule for Fibonacci {
+    fn name(&self) -> &str {
+        "fibonacci"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        let n = number(digits);
+        let (mut a, mut b) = (0, 1);
+        while a < n {
+            (a, b) = (b, a + b);
+        }
+        a == n
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Single
+    }
+}
+
+/// 06:55:36 (2¹⁶).
+pub struct PowerOfTwo;
+
+impl SignificanceRule for PowerOfTwo {
+    fn name(&self) -> &str {
+        "power-of-two"
+    }
+
+    fn label(&self) -> &str {
+        "power of 2"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        number(digits).is_power_of_two()
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Single
+    }
+}
+
+/// A round number of seconds (or minutes) since midnight, at least 1000: 02:46:40 is 10000s.
+pub struct Milestone;
+
+impl SignificanceRule for Milestone {
+    fn name(&self) -> &str {
+        "milestone"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        let mut elapsed = elapsed(digits);
+        if elapsed < 1000 {
+            return false;
+        }
+        while elapsed % 10 == 0 {
+            elapsed /= 10;
+        }
+        elapsed < 10
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Single
+    }
+}
+
+/// Every maths rule, in priority order.
+pub const RULES: &[&dyn SignificanceRule] = &[&Milestone, &PowerOfTwo, &Fibonacci, &Square, &Prime];
+
+/// Which maths rules are enabled.  All are off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct MathsRules {
+    pub prime: bool,
+    pub square: bool,
+    pub fibonacci: bool,
+    pub power_of_two: bool,
+    pub milestone: bool,
+}
+
+impl MathsRules {
+    /// Enabled rules, in the same order as [`RULES`].
+    pub fn enabled(&self) -> impl Iterator<Item = &'static dyn SignificanceRule> {
+        let enabled = [
+            self.milestone,
+            self.power_of_two,
+            self.fibonacci,
+            self.square,
+            self.prime,
+        ];
+        RULES
+            .iter()
+            .zip(enabled)
+            .filter_map(|(rule, enabled)| enabled.then_some(*rule))
+    }
+
+    pub fn explain_digits(&self, digits: &[u8]) -> Option<Significance<'static>> {
+        self.enabled().find_map(|rule| rule.explain(digits))
+    }
+
+    /// The first enabled rule matching `time`, if any.
+    pub fn explain(&self, time: impl Timelike) -> Option<Significance<'static>> {
+        self.explain_digits(&digits(&time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maths::*;
+    use chrono::NaiveTime;
+
+    fn rule(time: (u32, u32, u32), rules: MathsRules) -> Option<&'static str> {
+        let time = NaiveTime::from_hms_opt(time.0, time.1, time.2).unwrap();
+        rules.explain(time).map(|significance| significance.rule)
+    }
+
+    fn all() -> MathsRules {
+        MathsRules {
+            prime: true,
+            square: true,
+            fibonacci: true,
+            power_of_two: true,
+            milestone: true,
+        }
+    }
+
+    #[test]
+    fn digits_read_as_numbers() {
+        assert_eq!(number(&[1, 2, 3, 4, 5, 6]), 123456);
+        assert_eq!(number(&[0, 0, 4, 2]), 42);
+        assert_eq!(elapsed(&[0, 1, 0, 0, 1, 0]), 3610);
+        assert_eq!(elapsed(&[2, 3, 5, 9]), 1439);
+    }
+
+    #[test]
+    fn each_family_has_examples() {
+        assert_eq!(rule((12, 34, 57), all()), Some("prime"));
+        assert_eq!(rule((10, 24, 0), all()), Some("square"));
+        assert_eq!(rule((1, 9, 46), all()), Some("fibonacci"));
+        assert_eq!(rule((6, 55, 36), all()), Some("power-of-two"));
+        assert_eq!(rule((2, 46, 40), all()), Some("milestone"));
+        assert_eq!(rule((12, 34, 56), all()), None);
+    }
+
+    #[test]
+    fn families_toggle_individually() {
+        let time = (12, 34, 57);
+        assert_eq!(rule(time, MathsRules::default()), None);
+        let primes = MathsRules {
+            prime: true,
+            ..MathsRules::default()
+        };
+        assert_eq!(rule(time, primes), Some("prime"));
+        let others = MathsRules {
+            prime: false,
+            ..all()
+        };
+        assert_eq!(rule(time, others), None);
+    }
+
+    #[test]
+    fn rules_agree_with_brute_force() {
+        let squares: Vec<u32> = (0..500).map(|n| n * n).collect();
+        for n in 0..240_000u32 {
+            let digits = [
+                (n / 100_000) as u8,
+                (n / 10_000 % 10) as u8,
+                (n / 1000 % 10) as u8,
+                (n / 100 % 10) as u8,
+                (n / 10 % 10) as u8,
+                (n % 10) as u8,
+            ];
+            let prime = n >= 2 && (2..n).take_while(|d| d * d <= n).all(|d| n % d != 0);
+            assert_eq!(Prime.matches(&digits), prime, "{n}");
+            assert_eq!(Square.matches(&digits), squares.contains(&n), "{n}");
+            assert_eq!(PowerOfTwo.matches(&digits), n.count_ones() == 1, "{n}");
+        }
+    }
+
+    #[test]
+    fn milestones_are_round_numbers() {
+        let milestones: Vec<u32> = (0..86_400)
+            .map(|secs| NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap())
+            .filter(|time| Milestone.matches(&digits(time)))
+            .map(|time| time.num_seconds_from_midnight())
+            .collect();
+        let expected: Vec<u32> = (1..10)
+            .map(|n| n * 1000)
+            .chain((1..9).map(|n| n * 10_000))
+            .collect();
+        assert_eq!(milestones, expected);
+    }
+}
@@ -0,0 +1,298 @@
+//! User-defined significance rules, written in a small pattern language.
+//!
+//! A pattern is either a template, read digit by digit against `HH:MM:SS`, or a progression:
+//!
+//! - `A`–`Z` stand for digits.  The same letter is always the same digit, but different letters
+//!   may be equal too, so `AB:BA:AB` matches both 12:21:12 and 11:11:11.
+//! - `0`–`9` match themselves and `?` matches any one digit.
+//! - A final `*` matches whatever is left, so `AB:BA:*` ignores the seconds.  Without one the
+//!   pattern must cover every digit.
+//! - `:` is only there for readability and may go anywhere.
+//! - `d+1 run` matches when every digit is one more than the last.  Any step from `d-9` to `d+9`
+//!   works: `d-1 run` counts down and `d+0 run` is the same digit throughout.
+use alloc::string::String;
+use core::fmt;
+
+use crate::rules::{all_digits, FlashStyle, SignificanceRule, MAX_DIGITS};
+
+/// Why a pattern could not be parsed.  Positions count characters from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    Empty,
+    UnexpectedChar { column: usize, found: char },
+    MisplacedWildcard { column: usize },
+    TooLong(usize),
+    BadStep(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Empty => write!(f, "Pattern has no digits"),
+            PatternError::UnexpectedChar { column, found } => write!(
+                f,
+                "Unexpected '{found}' at column {column}: expected A-Z, 0-9, '?', '*' or ':'"
+            ),
+            PatternError::MisplacedWildcard { column } => {
+                write!(f, "'*' at column {column} must come last")
+            }
+            PatternError::TooLong(len) => write!(
+                f,
+                "Pattern has {len} digits but times have at most {MAX_DIGITS}"
+            ),
+            PatternError::BadStep(step) => {
+                write!(f, "Expected a run like 'd+1 run', not '{step}'")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PatternError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Element {
+    Letter(u8),
+    Digit(u8),
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Matcher {
+    Template {
+        elements: [Element; MAX_DIGITS],
+        len: usize,
+        rest: bool,
+    },
+    Run(i8),
+}
+
+/// A significance rule compiled from a pattern.  Its name and label are the pattern itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    matcher: Matcher,
+}
+
+impl Pattern {
+    pub fn parse(source: &str) -> Result<Pattern, PatternError> {
+        let trimmed = source.trim();
+        let matcher = match trimmed.strip_prefix('d') {
+            Some(step) => parse_run(step)?,
+            None => parse_template(trimmed)?,
+        };
+        Ok(Pattern {
+            source: trimmed.into(),
+            matcher,
+        })
+    }
+}
+
+fn parse_run(step: &str) -> Result<Matcher, PatternError> {
+    let bad_step = || PatternError::BadStep(["d", step].concat());
+    let step = step.strip_suffix("run").ok_or_else(bad_step)?.trim();
+    let (sign, digit) = match step.as_bytes() {
+        [b'+', digit] => (1, digit),
+        [b'-', digit] => (-1, digit),
+        _ => return Err(bad_step()),
+    };
+    if !digit.is_ascii_digit() {
+        return Err(bad_step());
+    }
+    Ok(Matcher::Run(sign * (digit - b'0') as i8))
+}
+
+fn parse_template(source: &str) -> Result<Matcher, PatternError> {
+    let mut elements = [Element::Any; MAX_DIGITS];
+    let mut len = 0;
+    let mut wildcard = None;
+    for (column, c) in source.chars().enumerate().map(|(i, c)| (i + 1, c)) {
+        let element = match c {
+            ':' => continue,
+            'A'..='Z' => Element::Letter(c as u8 - b'A'),
+            '0'..='9' => Element::Digit(c as u8 - b'0'),
+            '?' => Element::Any,
+            '*' => {
+                wildcard.get_or_insert(column);
+                continue;
+            }
+            found => return Err(PatternError::UnexpectedChar { column, found }),
+        };
+        if let Some(column) = wildcard {
+            return Err(PatternError::MisplacedWildcard { column });
+        }
+        if len < MAX_DIGITS {
+            elements[len] = element;
+        }
+        len += 1;
+    }
+    if len > MAX_DIGITS {
+        return Err(PatternError::TooLong(len));
+    }
+    if len == 0 {
+        return Err(PatternError::Empty);
+    }
+    Ok(Matcher::Template {
+        elements,
+        len,
+        rest: wildcard.is_some(),
+    })
+}
+
+impl Matcher {
+    fn matches(&self, digits: &[u8]) -> bool {
+        match self {
+            Matcher::Template {
+                elements,
+                len,
+                rest,
+            } => {
+                if digits.len() < *len || (!rest && digits.len() != *len) {
+                    return false;
+                }
+                let mut letters = [None; 26];
+                elements[..*len]
+                    .iter()
+                    .zip(digits)
+                    .all(|(element, &digit)| match *element {
+                        Element::Letter(letter) => {
+                            *letters[letter as usize].get_or_insert(digit) == digit
+                        }
+                        Element::Digit(expected) => digit == expected,
+                        Element::Any => true,
+                    })
+            }
+            Matcher::Run(step) => digits
+                .windows(2)
+                .all(|pair| pair[1] as i8 - pair[0] as i8 == *step),
+        }
+    }
+
+    /// Digits the pattern says something about.
+    fn participants(&self, digits: &[u8]) -> u16 {
+        match self {
+            Matcher::Template { elements, len, .. } => elements[..*len]
+                .iter()
+                .enumerate()
+                .filter(|(_, element)| **element != Element::Any)
+                .fold(0, |mask, (i, _)| mask | 1 << i),
+            Matcher::Run(_) => all_digits(digits),
+        }
+    }
+}
+
+impl SignificanceRule for Pattern {
+    fn name(&self) -> &str {
+        &self.source
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        self.matcher.matches(digits)
+    }
+
+    /// Patterns flash twice, like the built-in symmetries.
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Double
+    }
+
+    fn participants(&self, digits: &[u8]) -> u16 {
+        self.matcher.participants(digits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::*;
+    use crate::rules::*;
+    use chrono::NaiveTime;
+
+    fn digits_of(time: &str) -> Vec<u8> {
+        time.bytes()
+            .filter(|c| *c != b':')
+            .map(|c| c - b'0')
+            .collect()
+    }
+
+    fn matches(pattern: &str, time: &str) -> bool {
+        Pattern::parse(pattern).unwrap().matches(&digits_of(time))
+    }
+
+    #[test]
+    fn builtin_rules_can_be_written_as_patterns() {
+        let equivalents: [(&dyn SignificanceRule, &str); 5] = [
+            (&AllEqual, "AB:AB:AB"),
+            (&Run, "d+1 run"),
+            (&Mirror, "ABC:CBA"),
+            (&Palindrome, "AB:CC:BA"),
+            (&Doubles, "AA:BB:CC"),
+        ];
+        for secs in 0..86_400 {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap();
+            let digits = digits(&time);
+            for (rule, source) in equivalents {
+                let pattern = Pattern::parse(source).unwrap();
+                assert_eq!(
+                    pattern.matches(&digits),
+                    rule.matches(&digits),
+                    "'{source}' differs from {} at {time}",
+                    rule.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn templates_bind_letters() {
+        assert!(matches("AB:BA:AB", "12:21:12"));
+        assert!(matches("AB:BA:AB", "11:11:11"));
+        assert!(!matches("AB:BA:AB", "12:21:13"));
+        assert!(matches("ABC:CBA:*", "12:33:21"));
+        assert!(matches("AB:BA:*", "12:21:59"));
+        assert!(!matches("AB:BA", "12:21:59"));
+        assert!(matches("1?:??:00", "17:42:00"));
+    }
+
+    #[test]
+    fn runs_follow_step() {
+        assert!(matches("d+1 run", "12:34:56"));
+        assert!(matches("d-1 run", "54:32:10"));
+        assert!(matches(" d+0  run ", "11:11:11"));
+        assert!(!matches("d+1 run", "12:34:57"));
+    }
+
+    #[test]
+    fn explanation_marks_constrained_digits() {
+        let pattern = Pattern::parse("AB:BA:*").unwrap();
+        let explained = pattern.explain(&digits_of("12:21:59")).unwrap();
+        assert_eq!(explained.rule, "AB:BA:*");
+        assert_eq!(explained.digits, 0b1111);
+        let pattern = Pattern::parse("1?:??:00").unwrap();
+        let explained = pattern.explain(&digits_of("17:42:00")).unwrap();
+        assert_eq!(explained.digits, 0b11_0001);
+    }
+
+    #[test]
+    fn invalid_patterns_are_described() {
+        let error = |source| Pattern::parse(source).unwrap_err();
+        assert_eq!(error(""), PatternError::Empty);
+        assert_eq!(error("::*"), PatternError::Empty);
+        assert_eq!(
+            error("AB:xA"),
+            PatternError::UnexpectedChar {
+                column: 4,
+                found: 'x'
+            }
+        );
+        assert_eq!(
+            error("A*:BA"),
+            PatternError::MisplacedWildcard { column: 2 }
+        );
+        assert_eq!(error("AB:BA:AB:C"), PatternError::TooLong(7));
+        assert_eq!(error("d+10 run"), PatternError::BadStep("d+10 run".into()));
+        assert_eq!(error("d+1"), PatternError::BadStep("d+1".into()));
+        assert_eq!(
+            error("AB:xA").to_string(),
+            "Unexpected 'x' at column 4: expected A-Z, 0-9, '?', '*' or ':'"
+        );
+    }
+}
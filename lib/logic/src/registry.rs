@@ -0,0 +1,270 @@
+//! A runtime-configurable registry of significance rules.
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::fmt;
+
+use chrono::Timelike;
+
+use crate::maths::{Fibonacci, Milestone, PowerOfTwo, Prime, Square};
+use crate::rules::{
+    best, digits, AllEqual, Doubles, Mirror, Palindrome, Run, Significance, SignificanceRule,
+    BUILTIN, DEFAULT, MATCH,
+};
+
+/// Look up a built-in rule by name.  This includes the [`maths`](crate::maths) rules, which are not
+/// part of the default rule set.
+pub fn builtin(name: &str) -> Option<Box<dyn SignificanceRule>> {
+    let rule: Box<dyn SignificanceRule> = match name {
+        "all-equal" => Box::new(AllEqual),
+        "run" => Box::new(Run),
+        "mirror" => Box::new(Mirror),
+        "palindrome" => Box::new(Palindrome),
+        "doubles" => Box::new(Doubles),
+        "prime" => Box::new(Prime),
+        "square" => Box::new(Square),
+        "fibonacci" => Box::new(Fibonacci),
+        "power-of-two" => Box::new(PowerOfTwo),
+        "milestone" => Box::new(Milestone),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownRule(pub String);
+
+impl fmt::Display for UnknownRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown significance rule '{}'", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownRule {}
+
+struct Entry {
+    rule: Box<dyn SignificanceRule>,
+    enabled: bool,
+}
+
+/// An ordered registry of named rules, each of which can be enabled or disabled at runtime.
+///
+/// A time is significant if any enabled rule matches it.
+pub struct RuleSet {
+    entries: Vec<Entry>,
+}
+
+impl Default for RuleSet {
+    /// Every built-in rule, with only those in [`DEFAULT`] enabled.
+    fn default() -> RuleSet {
+        RuleSet::preset(DEFAULT)
+    }
+}
+
+impl RuleSet {
+    /// Every built-in rule, with only those in `enabled` enabled.
+    pub fn preset(enabled: &[&dyn SignificanceRule]) -> RuleSet {
+        let mut rules = RuleSet::empty();
+        for name in BUILTIN {
+            let rule = builtin(name).expect("Builtin rule missing from registry");
+            let enabled = enabled.iter().any(|default| default.name() == *name);
+            rules.entries.push(Entry { rule, enabled });
+        }
+        rules
+    }
+
+    pub fn empty() -> RuleSet {
+        RuleSet { entries: vec![] }
+    }
+
+    /// A rule set containing only the named built-in rules, enabled and in the order given.
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<RuleSet, UnknownRule> {
+        let mut rules = RuleSet::empty();
+        for name in names {
+            let name = name.as_ref();
+            let rule = builtin(name).ok_or_else(|| UnknownRule(name.into()))?;
+            rules.push(rule);
+        }
+        Ok(rules)
+    }
+
+    /// Add an enabled rule at the lowest priority.
+    pub fn push(&mut self, rule: Box<dyn SignificanceRule>) {
+        self.entries.push(Entry {
+            rule,
+            enabled: true,
+        });
+    }
+
+    fn position(&self, name: &str) -> Result<usize, UnknownRule> {
+        self.entries
+            .iter()
+            .position(|entry| entry.rule.name() == name)
+            .ok_or_else(|| UnknownRule(name.into()))
+    }
+
+    pub fn enable(&mut self, name: &str) -> Result<(), UnknownRule> {
+        let idx = self.position(name)?;
+        self.entries[idx].enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self, name: &str) -> Result<(), UnknownRule> {
+        let idx = self.position(name)?;
+        self.entries[idx].enabled = false;
+        Ok(())
+    }
+
+    /// Move the named rule to `index`, shifting the others down.  Indices past the end move the
+    /// rule to the end.
+    pub fn move_to(&mut self, name: &str, index: usize) -> Result<(), UnknownRule> {
+        let entry = self.entries.remove(self.position(name)?);
+        let index = index.min(self.entries.len());
+        self.entries.insert(index, entry);
+        Ok(())
+    }
+
+    /// Names of all rules in priority order, with whether they are enabled.
+    pub fn names(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.rule.name(), entry.enabled))
+    }
+
+    /// Enabled rules in priority order.
+    pub fn enabled(&self) -> impl Iterator<Item = &dyn SignificanceRule> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.rule.as_ref())
+    }
+
+    pub fn matches(&self, digits: &[u8]) -> bool {
+        self.enabled().any(|rule| rule.matches(digits))
+    }
+
+    /// The first enabled rule matching `digits`, if any.
+    pub fn explain_digits(&self, digits: &[u8]) -> Option<Significance<'_>> {
+        self.enabled().find_map(|rule| rule.explain(digits))
+    }
+
+    /// The highest score of any enabled rule.
+    pub fn score(&self, digits: &[u8]) -> u8 {
+        self.enabled()
+            .map(|rule| rule.score(digits))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Like [`RuleSet::explain_digits`], but also accepting near misses scoring at least
+    /// `threshold`.
+    pub fn explain_above(&self, digits: &[u8], threshold: u8) -> Option<Significance<'_>> {
+        if threshold >= MATCH {
+            return self.explain_digits(digits);
+        }
+        best(self.enabled(), digits, threshold)
+    }
+
+    pub fn is_significant(&self, time: impl Timelike) -> bool {
+        self.matches(&digits(&time))
+    }
+
+    /// Why `time` is significant, if it is.
+    pub fn explain(&self, time: impl Timelike) -> Option<Significance<'_>> {
+        self.explain_digits(&digits(&time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::registry::*;
+    use crate::rules::*;
+    use crate::significance::is_significant;
+    use chrono::prelude::*;
+
+    fn digits_of(time: &str) -> Vec<u8> {
+        time.bytes()
+            .filter(|c| *c != b':')
+            .map(|c| c - b'0')
+            .collect()
+    }
+
+    #[test]
+    fn builtin_names_are_consistent() {
+        assert_eq!(BUILTIN.len(), BUILTIN_RULES.len());
+        for (name, rule) in BUILTIN.iter().zip(BUILTIN_RULES) {
+            assert_eq!(builtin(name).unwrap().name(), *name);
+            assert_eq!(rule.name(), *name);
+        }
+        for rule in crate::maths::RULES {
+            assert_eq!(builtin(rule.name()).unwrap().name(), rule.name());
+        }
+    }
+    #[test]
+    fn default_rule_set_is_is_significant() {
+        let rules = RuleSet::default();
+        for h in 0..=23 {
+            for m in 0..=59 {
+                for s in 0..=59 {
+                    let time = Local.with_ymd_and_hms(2024, 1, 1, h, m, s).unwrap();
+                    assert_eq!(rules.is_significant(time), is_significant(time));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn disabled_rules_are_ignored() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 12, 34, 56).unwrap();
+        let mut rules = RuleSet::default();
+        rules.disable("run").unwrap();
+        assert!(!rules.is_significant(time));
+        rules.enable("run").unwrap();
+        assert!(rules.is_significant(time));
+    }
+
+    #[test]
+    fn rules_can_be_reordered() {
+        let mut rules = RuleSet::from_names(&["all-equal", "run", "mirror"]).unwrap();
+        rules.move_to("mirror", 0).unwrap();
+        rules.move_to("all-equal", 10).unwrap();
+        let names: Vec<&str> = rules.names().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["mirror", "run", "all-equal"]);
+    }
+
+    #[test]
+    fn explanation_follows_rule_order() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 11, 11, 11).unwrap();
+        let mut rules = RuleSet::default();
+        rules.enable("palindrome").unwrap();
+        assert_eq!(rules.explain(time).unwrap().rule, "all-equal");
+        rules.move_to("palindrome", 0).unwrap();
+        assert_eq!(rules.explain(time).unwrap().label, "palindrome");
+    }
+
+    #[test]
+    fn threshold_admits_near_misses() {
+        let rules = RuleSet::default();
+        let digits = digits_of("12:34:57");
+        assert_eq!(rules.explain_above(&digits, MATCH), None);
+        let significance = rules.explain_above(&digits, 80).unwrap();
+        assert_eq!((significance.rule, significance.score), ("run", 80));
+    }
+
+    #[test]
+    fn threshold_prefers_best_score() {
+        let mut rules = RuleSet::default();
+        rules.enable("doubles").unwrap();
+        // Half a run, but all pairs.
+        let significance = rules.explain_above(&digits_of("22:33:44"), 50).unwrap();
+        assert_eq!(significance.rule, "doubles");
+    }
+
+    #[test]
+    fn unknown_rules_are_rejected() {
+        assert_eq!(
+            RuleSet::from_names(&["run", "nonsense"]).err(),
+            Some(UnknownRule("nonsense".into()))
+        );
+        assert!(RuleSet::default().disable("nonsense").is_err());
+    }
+}
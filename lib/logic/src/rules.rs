@@ -0,0 +1,615 @@
+//! Significance rules.  This module is also compiled into `build.rs` to generate the
+//! precomputed tables, so it must not depend on the rest of the crate.
+
+use core::{fmt, ops::Deref};
+
+use chrono::Timelike;
+
+/// Why a time is significant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Significance<'a> {
+    /// Name of the rule which matched.
+    pub rule: &'a str,
+    /// Human-readable description of the pattern, short enough to fit on the screen.
+    pub label: &'a str,
+    /// Bitmask of the digits which took part in the pattern, bit 0 being the leftmost digit.
+    pub digits: u16,
+    /// How visible the pattern is, up to [`MATCH`] for a full match.
+    pub score: u8,
+    /// How the lamp flashes for the rule.
+    pub flash: FlashStyle,
+}
+
+/// How the lamp flashes when a rule fires, so patterns can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashStyle {
+    Single,
+    Double,
+    Triple,
+}
+
+impl FlashStyle {
+    /// Number of flashes.
+    pub fn count(self) -> usize {
+        match self {
+            FlashStyle::Single => 1,
+            FlashStyle::Double => 2,
+            FlashStyle::Triple => 3,
+        }
+    }
+}
+
+/// Score of a full match.  Near misses score less, depending on how visible they are.
+pub const MATCH: u8 = 100;
+
+impl Significance<'_> {
+    /// Whether the digit at `index` (from the left) took part in the pattern.
+    pub fn includes(&self, index: usize) -> bool {
+        index < 16 && self.digits & (1 << index) != 0
+    }
+}
+
+impl fmt::Display for Significance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+/// A single pattern which can make a time significant.
+///
+/// Rules are handed the displayed digits of the time (`HHMMSS`) and decide whether they match.
+pub trait SignificanceRule: Send + Sync {
+    /// Unique name, used to enable, disable and order the rule in a
+    /// [`RuleSet`](crate::registry::RuleSet).
+    fn name(&self) -> &str;
+
+    /// Human-readable description of the pattern.
+    fn label(&self) -> &str {
+        self.name()
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool;
+
+    /// How the lamp flashes when this rule fires.
+    fn flash(&self) -> FlashStyle;
+
+    /// How visible the pattern is in `digits`, from 0 to [`MATCH`].  Only a full match may score
+    /// [`MATCH`]; by default nothing else scores at all.
+    fn score(&self, digits: &[u8]) -> u8 {
+        if self.matches(digits) {
+            MATCH
+        } else {
+            0
+        }
+    }
+
+    /// The digits taking part in the pattern, as a [`Significance::digits`] mask.  For a near
+    /// miss these are the digits which fit.  By default every digit takes part.
+    fn participants(&self, digits: &[u8]) -> u16 {
+        all_digits(digits)
+    }
+
+    /// Why `digits` match this rule, or `None` if they don't.
+    fn explain(&self, digits: &[u8]) -> Option<Significance<'_>> {
+        self.matches(digits).then(|| Significance {
+            rule: self.name(),
+            label: self.label(),
+            digits: self.participants(digits),
+            score: MATCH,
+            flash: self.flash(),
+        })
+    }
+}
+
+pub(crate) fn all_digits(digits: &[u8]) -> u16 {
+    ((1u32 << digits.len()) - 1) as u16
+}
+
+/// `MATCH` scaled by `n / total`.
+fn fraction(n: usize, total: usize) -> u8 {
+    (n * MATCH as usize / total) as u8
+}
+
+/// Differences between consecutive digits.
+fn diffs(digits: &[u8]) -> [i8; MAX_DIGITS - 1] {
+    let mut diffs = [0; MAX_DIGITS - 1];
+    for (diff, window) in diffs.iter_mut().zip(digits.windows(2)) {
+        *diff = window[1] as i8 - window[0] as i8;
+    }
+    diffs
+}
+
+/// Mask of the digits at `indices`.
+fn mask(indices: impl Iterator<Item = usize>) -> u16 {
+    indices.fold(0, |mask, i| mask | 1 << i)
+}
+
+/// Indices of the first digit of each mirrored pair which is the same.
+fn mirrored_pairs(digits: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    let half = digits.len() / 2;
+    (0..half).filter(move |&i| digits[i] == digits[digits.len() - 1 - i])
+}
+
+/// Indices of the first digit of each pair (HH, MM, SS) made of a repeated digit.
+fn repeated_pairs(digits: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    (0..digits.len())
+        .step_by(2)
+        .filter(move |&i| digits[i] == digits[i + 1])
+}
+
+/// The first and last index of the longest run of digits each one more than the last, if
+/// there's a run at all.
+fn longest_run(digits: &[u8]) -> Option<(usize, usize)> {
+    let mut longest: Option<(usize, usize)> = None;
+    let mut start = 0;
+    for (i, window) in digits.windows(2).enumerate() {
+        if window[1] as i8 - window[0] as i8 != 1 {
+            start = i + 1;
+            continue;
+        }
+        if longest.map_or(true, |(first, last)| i + 1 - start > last - first) {
+            longest = Some((start, i + 1));
+        }
+    }
+    longest
+}
+
+/// Hours, minutes and seconds all the same: 12:12:12.
+pub struct AllEqual;
+
+impl SignificanceRule for AllEqual {
+    fn name(&self) -> &str {
+        "all-equal"
+    }
+
+    fn label(&self) -> &str {
+        "all same"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        digits.chunks(2).all(|pair| pair == &digits[..2])
+    }
+
+    /// The rarest of the built-in rules, so it gets the most flashes.
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Triple
+    }
+
+    /// Fraction of the minute and second digits matching the hour.
+    fn score(&self, digits: &[u8]) -> u8 {
+        let equal = self.participants(digits).count_ones() as usize - 2;
+        fraction(equal, digits.len() - 2)
+    }
+
+    /// The hour, and whichever minute and second digits match it.
+    fn participants(&self, digits: &[u8]) -> u16 {
+        mask((0..digits.len()).filter(|&i| i < 2 || digits[i] == digits[i % 2]))
+    }
+}
+
+/// Every digit one more than the last: 12:34:56.
+pub struct Run;
+
+impl SignificanceRule for Run {
+    fn name(&self) -> &str {
+        "run"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        digits
+            .windows(2)
+            .all(|window| window[1] as i8 - window[0] as i8 == 1)
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Single
+    }
+
+    /// Length of the longest run, as a fraction of the whole time.
+    fn score(&self, digits: &[u8]) -> u8 {
+        let longest = longest_run(digits).map_or(0, |(first, last)| last - first);
+        fraction(longest, digits.len() - 1)
+    }
+
+    /// The longest run.
+    fn participants(&self, digits: &[u8]) -> u16 {
+        longest_run(digits).map_or(0, |(first, last)| mask(first..=last))
+    }
+}
+
+/// Differences between digits mirrored about the middle, with matching ends: 12:33:21.  Only
+/// defined for `HHMMSS`; shorter times never match.
+pub struct Mirror;
+
+impl SignificanceRule for Mirror {
+    fn name(&self) -> &str {
+        "mirror"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        if digits.len() != MAX_DIGITS {
+            return false;
+        }
+        let same_start_end = digits[0] == digits[3] && digits[2] == digits[5];
+        let same_start_end_reversed = digits[0] == digits[5] && digits[2] == digits[3];
+        let diffs = diffs(digits);
+        diffs[0] == -diffs[4]
+            && diffs[1] == -diffs[3]
+            && (same_start_end || same_start_end_reversed)
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Double
+    }
+
+    /// Mirrored differences without matching ends are hard to spot, and get harder the bigger
+    /// the jumps between digits.
+    fn score(&self, digits: &[u8]) -> u8 {
+        if digits.len() != MAX_DIGITS {
+            return 0;
+        }
+        if self.matches(digits) {
+            return MATCH;
+        }
+        let mirrored = mirrored_diffs(digits).count() as u8;
+        let spread: u8 = diffs(digits).iter().map(|diff| diff.unsigned_abs()).sum();
+        (30 * mirrored).saturating_sub(3 * spread)
+    }
+
+    /// The digits either side of each mirrored difference.
+    fn participants(&self, digits: &[u8]) -> u16 {
+        if digits.len() != MAX_DIGITS {
+            return 0;
+        }
+        mask(mirrored_diffs(digits).flat_map(|(a, b)| [a, a + 1, b, b + 1]))
+    }
+}
+
+/// The pairs of differences (by the index of the first digit of each) which mirror each other
+/// about the middle of `HHMMSS`.
+fn mirrored_diffs(digits: &[u8]) -> impl Iterator<Item = (usize, usize)> {
+    let diffs = diffs(digits);
+    [(0, 4), (1, 3)]
+        .into_iter()
+        .filter(move |(a, b)| diffs[*a] == -diffs[*b])
+}
+
+/// Digits reading the same backwards: 01:22:10.
+pub struct Palindrome;
+
+impl SignificanceRule for Palindrome {
+    fn name(&self) -> &str {
+        "palindrome"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        digits.iter().eq(digits.iter().rev())
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Double
+    }
+
+    fn score(&self, digits: &[u8]) -> u8 {
+        fraction(mirrored_pairs(digits).count(), digits.len() / 2)
+    }
+
+    /// The mirrored pairs which are the same.
+    fn participants(&self, digits: &[u8]) -> u16 {
+        mask(mirrored_pairs(digits).flat_map(|i| [i, digits.len() - 1 - i]))
+    }
+}
+
+/// Each of hours, minutes and seconds a repeated digit: 11:22:33.
+pub struct Doubles;
+
+impl SignificanceRule for Doubles {
+    fn name(&self) -> &str {
+        "doubles"
+    }
+
+    fn label(&self) -> &str {
+        "pairs"
+    }
+
+    fn matches(&self, digits: &[u8]) -> bool {
+        digits.chunks(2).all(|pair| pair[0] == pair[1])
+    }
+
+    fn flash(&self) -> FlashStyle {
+        FlashStyle::Double
+    }
+
+    fn score(&self, digits: &[u8]) -> u8 {
+        fraction(repeated_pairs(digits).count(), digits.len() / 2)
+    }
+
+    /// The pairs made of a repeated digit.
+    fn participants(&self, digits: &[u8]) -> u16 {
+        mask(repeated_pairs(digits).flat_map(|i| [i, i + 1]))
+    }
+}
+
+/// The best scoring of `rules` for `digits`, if it scores at least `threshold`.  Ties go to the
+/// earlier rule.
+pub fn best<'a>(
+    rules: impl Iterator<Item = &'a dyn SignificanceRule>,
+    digits: &[u8],
+    threshold: u8,
+) -> Option<Significance<'a>> {
+    rules
+        .map(|rule| {
+            let score = rule.score(digits);
+            let significance = rule.explain(digits).unwrap_or(Significance {
+                rule: rule.name(),
+                label: rule.label(),
+                digits: rule.participants(digits),
+                score,
+                flash: rule.flash(),
+            });
+            Significance {
+                score,
+                ..significance
+            }
+        })
+        .filter(|significance| significance.score >= threshold)
+        .fold(None, |best: Option<Significance>, candidate| match best {
+            Some(best) if best.score >= candidate.score => Some(best),
+            _ => Some(candidate),
+        })
+}
+
+/// The rules making up the default rule set, in priority order.
+pub const DEFAULT: &[&dyn SignificanceRule] = &[&AllEqual, &Run, &Mirror];
+
+/// The rules used when only hours and minutes are shown, in priority order.
+pub const MINUTE: &[&dyn SignificanceRule] = &[&AllEqual, &Run, &Palindrome];
+
+/// Every built-in rule, in priority order.
+pub const BUILTIN_RULES: &[&dyn SignificanceRule] =
+    &[&AllEqual, &Run, &Mirror, &Palindrome, &Doubles];
+
+/// Names of every built-in rule.
+pub const BUILTIN: &[&str] = &["all-equal", "run", "mirror", "palindrome", "doubles"];
+
+/// Most digits a [`Digits`] can hold: `HHMMSS`.
+pub const MAX_DIGITS: usize = 6;
+
+/// Digit values shown on the clock, most significant first.  Held inline, so splitting a time
+/// into digits doesn't allocate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digits {
+    digits: [u8; MAX_DIGITS],
+    len: usize,
+}
+
+impl Digits {
+    /// Split two-digit values into their digits.  Values past [`MAX_DIGITS`] are dropped.
+    pub fn from_pairs(values: &[u32]) -> Digits {
+        let mut digits = [0; MAX_DIGITS];
+        for (pair, value) in digits.chunks_mut(2).zip(values) {
+            pair[0] = (value / 10 % 10) as u8;
+            pair[1] = (value % 10) as u8;
+        }
+        Digits {
+            digits,
+            len: (values.len() * 2).min(MAX_DIGITS),
+        }
+    }
+
+    pub fn from_hms(hour: u32, minute: u32, second: u32) -> Digits {
+        Digits::from_pairs(&[hour, minute, second])
+    }
+}
+
+impl Deref for Digits {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.digits[..self.len]
+    }
+}
+
+/// Split a time into the digits displayed on the clock.
+pub fn digits<T: Timelike>(time: &T) -> Digits {
+    Digits::from_hms(time.hour(), time.minute(), time.second())
+}
+
+/// Split a time into hour and minute digits, for clocks not showing seconds.
+pub fn minute_digits<T: Timelike>(time: &T) -> Digits {
+    Digits::from_pairs(&[time.hour(), time.minute()])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::*;
+    use chrono::prelude::*;
+
+    fn digits_of(time: &str) -> Vec<u8> {
+        time.bytes()
+            .filter(|c| *c != b':')
+            .map(|c| c - b'0')
+            .collect()
+    }
+
+    #[test]
+    fn digits_are_values() {
+        let time = NaiveTime::from_hms_opt(12, 34, 56).unwrap();
+        assert_eq!(*digits(&time), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(*Digits::from_pairs(&[3, 14]), [0, 3, 1, 4]);
+    }
+
+    #[test]
+    fn minute_rules_match_examples() {
+        let rule = |time| {
+            let digits = digits_of(time);
+            MINUTE
+                .iter()
+                .find(|rule| rule.matches(&digits))
+                .map(|rule| rule.name())
+        };
+        assert_eq!(rule("11:11"), Some("all-equal"));
+        assert_eq!(rule("12:12"), Some("all-equal"));
+        assert_eq!(rule("12:34"), Some("run"));
+        assert_eq!(rule("12:21"), Some("palindrome"));
+        assert_eq!(rule("12:35"), None);
+    }
+
+    #[test]
+    fn rules_accept_minutes() {
+        let digits = minute_digits(&NaiveTime::from_hms_opt(12, 21, 59).unwrap());
+        assert_eq!(*digits, [1, 2, 2, 1]);
+        for rule in BUILTIN_RULES {
+            assert!(rule.score(&digits) <= MATCH);
+        }
+        assert!(!Mirror.matches(&digits));
+    }
+
+    #[test]
+    fn builtin_rules_match_examples() {
+        assert!(AllEqual.matches(&digits_of("12:12:12")));
+        assert!(Run.matches(&digits_of("12:34:56")));
+        assert!(Mirror.matches(&digits_of("12:33:21")));
+        assert!(Palindrome.matches(&digits_of("01:22:10")));
+        assert!(Doubles.matches(&digits_of("11:22:33")));
+    }
+
+    #[test]
+    fn builtin_rules_reject_ordinary_time() {
+        let digits = digits_of("10:27:43");
+        for rule in BUILTIN_RULES {
+            assert!(!rule.matches(&digits), "{} matched", rule.name());
+        }
+    }
+
+    #[test]
+    fn only_full_matches_score_match() {
+        for secs in 0..86_400 {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap();
+            let digits = digits(&time);
+            for rule in BUILTIN_RULES {
+                assert_eq!(rule.score(&digits) == MATCH, rule.matches(&digits));
+            }
+        }
+    }
+
+    /// Every possible string of six digits, valid time or not.
+    fn all_digit_strings() -> impl Iterator<Item = [u8; 6]> {
+        (0..1_000_000u32).map(|n| {
+            let mut digits = [0; 6];
+            for (i, digit) in digits.iter_mut().rev().enumerate() {
+                *digit = (n / 10u32.pow(i as u32) % 10) as u8;
+            }
+            digits
+        })
+    }
+
+    fn reversed(digits: [u8; 6]) -> [u8; 6] {
+        let mut reversed = digits;
+        reversed.reverse();
+        reversed
+    }
+
+    #[test]
+    fn mirror_rules_are_symmetric() {
+        let symmetric: &[&dyn SignificanceRule] = &[&AllEqual, &Mirror, &Palindrome, &Doubles];
+        for digits in all_digit_strings() {
+            for rule in symmetric {
+                assert_eq!(
+                    rule.matches(&digits),
+                    rule.matches(&reversed(digits)),
+                    "{} not symmetric for {digits:?}",
+                    rule.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reversed_run_descends() {
+        for digits in all_digit_strings().filter(|digits| Run.matches(digits)) {
+            let reversed = reversed(digits);
+            assert!(reversed.windows(2).all(|pair| pair[0] == pair[1] + 1));
+        }
+    }
+
+    #[test]
+    fn scores_never_exceed_match() {
+        for digits in all_digit_strings().step_by(7) {
+            for rule in BUILTIN_RULES {
+                assert!(rule.score(&digits) <= MATCH);
+            }
+        }
+    }
+
+    /// Run with `--nocapture` to see how often each rule fires in a day.
+    #[test]
+    fn rule_hit_counts_per_day() {
+        let mut counts = [0; BUILTIN.len()];
+        let mut any_default = 0;
+        for secs in 0..86_400 {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap();
+            let digits = digits(&time);
+            for (count, rule) in counts.iter_mut().zip(BUILTIN_RULES) {
+                *count += rule.matches(&digits) as usize;
+            }
+            any_default += DEFAULT.iter().any(|rule| rule.matches(&digits)) as usize;
+        }
+        for (count, rule) in counts.iter().zip(BUILTIN_RULES) {
+            println!("{:>12} {count:>5}", rule.name());
+        }
+        println!("{:>12} {any_default:>5}", "default");
+        assert_eq!(any_default, crate::table::DEFAULT.len());
+    }
+
+    #[test]
+    fn full_matches_take_in_every_digit() {
+        for secs in 0..86_400 {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap();
+            let digits = digits(&time);
+            for rule in BUILTIN_RULES.iter().filter(|rule| rule.matches(&digits)) {
+                assert_eq!(rule.participants(&digits), 0b11_1111, "{}", rule.name());
+            }
+        }
+    }
+
+    #[test]
+    fn explanations_carry_the_rules_flash() {
+        let flash = |time| {
+            let digits = digits_of(time);
+            let explained = BUILTIN_RULES.iter().find_map(|rule| rule.explain(&digits));
+            explained.unwrap().flash
+        };
+        assert_eq!(flash("11:11:11"), FlashStyle::Triple);
+        assert_eq!(flash("12:34:56"), FlashStyle::Single);
+        assert_eq!(flash("12:33:21"), FlashStyle::Double);
+        let near = best(
+            [&Run as &dyn SignificanceRule].into_iter(),
+            &digits_of("12:34:57"),
+            50,
+        );
+        assert_eq!(near.unwrap().flash, FlashStyle::Single);
+    }
+
+    #[test]
+    fn near_misses_mark_the_digits_which_fit() {
+        let marked = |rule: &'static dyn SignificanceRule, time| {
+            best([rule].into_iter(), &digits_of(time), 1)
+                .unwrap()
+                .digits
+        };
+        assert_eq!(marked(&Doubles, "11:23:44"), 0b11_0011);
+        assert_eq!(marked(&AllEqual, "12:12:33"), 0b00_1111);
+        assert_eq!(marked(&Run, "91:23:45"), 0b11_1110);
+        assert_eq!(marked(&Palindrome, "12:30:21"), 0b11_0011);
+        assert_eq!(marked(&Mirror, "01:23:54"), 0b11_0011);
+    }
+
+    #[test]
+    fn near_misses_score_partially() {
+        assert_eq!(AllEqual.score(&digits_of("12:12:13")), 75);
+        assert_eq!(Run.score(&digits_of("12:34:57")), 80);
+        assert_eq!(Palindrome.score(&digits_of("12:30:21")), 66);
+        assert_eq!(Doubles.score(&digits_of("11:22:34")), 66);
+    }
+}
@@ -0,0 +1,325 @@
+//! When the clock dims, and when it blanks the screen altogether.
+//!
+//! A [`BrightnessSchedule`] splits the day into day and night, at fixed times or around sunrise
+//! and sunset, and can blank the screen for part of the night.
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+
+use crate::sun::{Location, Sun};
+
+/// A time of day, either on the clock or relative to the sun.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TimeOfDay {
+    At(NaiveTime),
+    /// Minutes after sunrise, or before if negative.
+    Sunrise(i32),
+    /// Minutes after sunset, or before if negative.
+    Sunset(i32),
+}
+
+/// Part of the day, wrapping past midnight if `until` comes before `from`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Window {
+    pub from: TimeOfDay,
+    pub until: TimeOfDay,
+}
+
+/// How bright the screen and the lamp are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level {
+    /// MAX7219 intensity, from 0 to 15.
+    pub screen: u8,
+    /// Percent of the configured lamp colour.
+    pub lamp: u8,
+}
+
+/// Where the schedule has got to: how bright things are, and whether the screen is blank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Setting {
+    pub level: Level,
+    pub blank: bool,
+}
+
+/// Brightness by time of day.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct BrightnessSchedule {
+    /// Off by default, which stays at `day_level` all the time.
+    pub enabled: bool,
+    /// Needed for times relative to the sun.  Without it sunrise is taken to be 06:00 and sunset
+    /// 18:00.
+    pub location: Option<Location>,
+    /// When it's day; it's night the rest of the time.
+    pub day: Window,
+    pub day_level: Level,
+    pub night_level: Level,
+    /// When the screen is blank, if ever.
+    pub blank: Option<Window>,
+}
+
+fn time(hour: u32, min: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, min, 0).expect("Valid time")
+}
+
+impl Default for BrightnessSchedule {
+    fn default() -> BrightnessSchedule {
+        BrightnessSchedule {
+            enabled: false,
+            location: None,
+            day: Window {
+                from: TimeOfDay::At(time(7, 0)),
+                until: TimeOfDay::At(time(22, 0)),
+            },
+            // The brightness the clock always had before it could be scheduled.
+            day_level: Level {
+                screen: 1,
+                lamp: 100,
+            },
+            night_level: Level {
+                screen: 0,
+                lamp: 20,
+            },
+            blank: None,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// The time on the clock, given `sun` in local time.
+    fn on(self, sun: &Sun) -> NaiveTime {
+        let offset = |time: NaiveTime, minutes: i32| time + Duration::minutes(minutes.into());
+        match (self, sun) {
+            (TimeOfDay::At(time), _) => time,
+            (TimeOfDay::Sunrise(minutes), Sun::RisesAndSets { sunrise, .. }) => {
+                offset(sunrise.time(), minutes)
+            }
+            (TimeOfDay::Sunset(minutes), Sun::RisesAndSets { sunset, .. }) => {
+                offset(sunset.time(), minutes)
+            }
+            // Offsets are ignored when the sun doesn't rise or set, so the day stays whole (or
+            // empty) rather than wrapping round.
+            (TimeOfDay::Sunrise(_), Sun::AlwaysUp) => NaiveTime::MIN,
+            (TimeOfDay::Sunset(_), Sun::AlwaysUp) => {
+                NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).expect("Valid time")
+            }
+            (_, Sun::AlwaysDown) => time(12, 0),
+        }
+    }
+}
+
+impl Window {
+    fn contains(&self, now: NaiveTime, sun: &Sun) -> bool {
+        let (from, until) = (self.from.on(sun), self.until.on(sun));
+        if from <= until {
+            from <= now && now < until
+        } else {
+            from <= now || now < until
+        }
+    }
+}
+
+impl BrightnessSchedule {
+    /// Sunrise and sunset in local time on `now`'s day.
+    fn sun<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Sun {
+        let Some(location) = self.location else {
+            let today = now.date_naive();
+            return Sun::RisesAndSets {
+                sunrise: today.and_time(time(6, 0)),
+                sunset: today.and_time(time(18, 0)),
+            };
+        };
+        let local = |utc| Utc.from_utc_datetime(&utc).with_timezone(&now.timezone());
+        match Sun::on(now.date_naive(), location) {
+            Sun::RisesAndSets { sunrise, sunset } => Sun::RisesAndSets {
+                sunrise: local(sunrise).naive_local(),
+                sunset: local(sunset).naive_local(),
+            },
+            sun => sun,
+        }
+    }
+
+    /// What the schedule calls for at `now`.
+    pub fn at<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Setting {
+        if !self.enabled {
+            return Setting {
+                level: self.day_level,
+                blank: false,
+            };
+        }
+        let sun = self.sun(now);
+        let time = now.time();
+        let level = if self.day.contains(time, &sun) {
+            self.day_level
+        } else {
+            self.night_level
+        };
+        let blank = self.blank.is_some_and(|window| window.contains(time, &sun));
+        Setting { level, blank }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone};
+
+    use crate::schedule::{BrightnessSchedule, Level, Setting, TimeOfDay, Window};
+    use crate::sun::Location;
+
+    const DAY: Level = Level {
+        screen: 8,
+        lamp: 100,
+    };
+    const NIGHT: Level = Level { screen: 0, lamp: 5 };
+
+    fn at(h: u32, m: u32) -> TimeOfDay {
+        TimeOfDay::At(NaiveTime::from_hms_opt(h, m, 0).unwrap())
+    }
+
+    /// `h:m` on `y-mo-d` an hour ahead of UTC, as in British summer time.
+    fn bst(y: i32, mo: u32, d: u32, h: u32, m: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(y, mo, d, h, m, 0)
+            .unwrap()
+    }
+
+    fn schedule(day: Window, blank: Option<Window>) -> BrightnessSchedule {
+        BrightnessSchedule {
+            enabled: true,
+            day,
+            day_level: DAY,
+            night_level: NIGHT,
+            blank,
+            ..BrightnessSchedule::default()
+        }
+    }
+
+    fn level(schedule: &BrightnessSchedule, h: u32, m: u32) -> Level {
+        schedule.at(&bst(2023, 6, 21, h, m)).level
+    }
+
+    #[test]
+    fn fixed_times() {
+        let schedule = schedule(
+            Window {
+                from: at(7, 0),
+                until: at(22, 0),
+            },
+            None,
+        );
+        assert_eq!(level(&schedule, 6, 59), NIGHT);
+        assert_eq!(level(&schedule, 7, 0), DAY);
+        assert_eq!(level(&schedule, 21, 59), DAY);
+        assert_eq!(level(&schedule, 22, 0), NIGHT);
+        assert_eq!(level(&schedule, 0, 0), NIGHT);
+    }
+
+    #[test]
+    fn blank_across_midnight() {
+        let day = Window {
+            from: at(7, 0),
+            until: at(22, 0),
+        };
+        let blank = Window {
+            from: at(23, 30),
+            until: at(6, 0),
+        };
+        let schedule = schedule(day, Some(blank));
+        let blank = |h, m| schedule.at(&bst(2023, 6, 21, h, m)).blank;
+        assert!(!blank(23, 29));
+        assert!(blank(23, 30));
+        assert!(blank(3, 0));
+        assert!(!blank(6, 0));
+        assert!(!blank(12, 0));
+    }
+
+    #[test]
+    fn around_the_sun() {
+        let schedule = BrightnessSchedule {
+            location: Some(Location {
+                latitude: 51.5074,
+                longitude: -0.1278,
+            }),
+            ..schedule(
+                Window {
+                    from: TimeOfDay::Sunrise(0),
+                    until: TimeOfDay::Sunset(30),
+                },
+                None,
+            )
+        };
+        // Sunrise is at 04:43 and sunset at 21:21 local time.
+        assert_eq!(level(&schedule, 4, 40), NIGHT);
+        assert_eq!(level(&schedule, 4, 46), DAY);
+        assert_eq!(level(&schedule, 21, 48), DAY);
+        assert_eq!(level(&schedule, 21, 54), NIGHT);
+    }
+
+    #[test]
+    fn without_a_location() {
+        let schedule = schedule(
+            Window {
+                from: TimeOfDay::Sunrise(-60),
+                until: TimeOfDay::Sunset(0),
+            },
+            None,
+        );
+        assert_eq!(level(&schedule, 4, 59), NIGHT);
+        assert_eq!(level(&schedule, 5, 0), DAY);
+        assert_eq!(level(&schedule, 18, 0), NIGHT);
+    }
+
+    #[test]
+    fn polar() {
+        let schedule = BrightnessSchedule {
+            location: Some(Location {
+                latitude: 69.6492,
+                longitude: 18.9553,
+            }),
+            ..schedule(
+                Window {
+                    from: TimeOfDay::Sunrise(30),
+                    until: TimeOfDay::Sunset(-30),
+                },
+                None,
+            )
+        };
+        assert_eq!(schedule.at(&bst(2023, 6, 21, 0, 0)).level, DAY);
+        assert_eq!(schedule.at(&bst(2023, 6, 21, 23, 59)).level, DAY);
+        assert_eq!(schedule.at(&bst(2023, 12, 21, 12, 0)).level, NIGHT);
+    }
+
+    #[test]
+    fn disabled() {
+        let schedule = BrightnessSchedule {
+            enabled: false,
+            ..schedule(
+                Window {
+                    from: at(7, 0),
+                    until: at(22, 0),
+                },
+                Some(Window {
+                    from: at(0, 0),
+                    until: at(6, 0),
+                }),
+            )
+        };
+        assert_eq!(
+            schedule.at(&bst(2023, 6, 21, 3, 0)),
+            Setting {
+                level: DAY,
+                blank: false
+            }
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use alloc::vec::Vec;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Timelike};
+
+use crate::format::HourFormat;
+use crate::registry::RuleSet;
+use crate::rules::{digits, SignificanceRule, DEFAULT};
+use crate::table::{self, Table, SECONDS_PER_DAY};
+
+/// Every significant second of the day for a rule set, for answering "when is the next one?"
+/// without evaluating the rules second by second.
+///
+/// Significance depends only on the time of day, so the day is evaluated once up front and
+/// searches are then a binary search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    seconds: Vec<u32>,
+}
+
+impl Default for Schedule {
+    fn default() -> Schedule {
+        Schedule::new(&RuleSet::default())
+    }
+}
+
+impl Schedule {
+    pub fn new(rules: &RuleSet) -> Schedule {
+        Schedule::with_hour_format(rules, HourFormat::TwentyFour)
+    }
+
+    /// Evaluate the rules against the time as shown on a clock using `format`.  The default rule
+    /// set on a 24-hour clock is read from the precomputed [`table`] instead.
+    pub fn with_hour_format(rules: &RuleSet, format: HourFormat) -> Schedule {
+        let day = 0..SECONDS_PER_DAY as u32;
+        let seconds = match (format, precomputed(rules)) {
+            (HourFormat::TwentyFour, Some(table)) => {
+                day.filter(|&secs| table.contains(secs)).collect()
+            }
+            _ => day
+                .filter(|&secs| rules.matches(&digits(&format.displayed(&time_of_day(secs)))))
+                .collect(),
+        };
+        Schedule { seconds }
+    }
+
+    /// Number of significant seconds in a day.
+    pub fn len(&self) -> usize {
+        self.seconds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seconds.is_empty()
+    }
+
+    /// The first significant time strictly after `time`.
+    pub fn next_after<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        Significant::new(self, time.clone(), false).next()
+    }
+
+    /// The last significant time strictly before `time`.
+    pub fn previous_before<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        if self.is_empty() {
+            return None;
+        }
+        let tz = time.timezone();
+        let local = time.naive_local();
+        let mut date = local.date();
+        let secs = seconds_of(&local.time());
+        let mut candidates = &self.seconds[..self.seconds.partition_point(|&s| s <= secs)];
+        loop {
+            for &secs in candidates.iter().rev() {
+                match at(&tz, date, secs) {
+                    Some(candidate) if candidate < *time => return Some(candidate),
+                    _ => (),
+                }
+            }
+            date = date.pred_opt()?;
+            candidates = &self.seconds;
+        }
+    }
+
+    /// Every significant time from `start` (inclusive) to `end` (exclusive), in order.
+    pub fn between<'a, Tz: TimeZone + 'a>(
+        &'a self,
+        start: &DateTime<Tz>,
+        end: &DateTime<Tz>,
+    ) -> impl Iterator<Item = DateTime<Tz>> + 'a {
+        let end = end.clone();
+        Significant::new(self, start.clone(), true).take_while(move |time| *time < end)
+    }
+}
+
+/// Iterator over significant times after a given point, continuing indefinitely into the future.
+pub struct Significant<'a, Tz: TimeZone> {
+    schedule: &'a Schedule,
+    start: DateTime<Tz>,
+    inclusive: bool,
+    date: NaiveDate,
+    index: usize,
+}
+
+impl<'a, Tz: TimeZone> Significant<'a, Tz> {
+    fn new(schedule: &'a Schedule, start: DateTime<Tz>, inclusive: bool) -> Self {
+        let local = start.naive_local();
+        let secs = seconds_of(&local.time());
+        let index = schedule.seconds.partition_point(|&s| s < secs);
+        Significant {
+            schedule,
+            date: local.date(),
+            start,
+            inclusive,
+            index,
+        }
+    }
+}
+
+impl<Tz: TimeZone> Iterator for Significant<'_, Tz> {
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.schedule.is_empty() {
+            return None;
+        }
+        let tz = self.start.timezone();
+        loop {
+            if self.index >= self.schedule.seconds.len() {
+                self.date = self.date.succ_opt()?;
+                self.index = 0;
+            }
+            let secs = self.schedule.seconds[self.index];
+            self.index += 1;
+            // Local times skipped by a DST change don't exist, and repeated ones are only
+            // reported the first time round.
+            match at(&tz, self.date, secs) {
+                Some(time) if time > self.start || (self.inclusive && time == self.start) => {
+                    return Some(time)
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// The table precomputed for `rules`, if they're exactly the default set.
+fn precomputed(rules: &RuleSet) -> Option<&'static Table> {
+    let default = DEFAULT.iter().map(|rule| rule.name());
+    rules
+        .enabled()
+        .map(SignificanceRule::name)
+        .eq(default)
+        .then_some(&table::DEFAULT)
+}
+
+fn time_of_day(secs: u32) -> NaiveTime {
+    NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).expect("Seconds out of range for day")
+}
+
+fn seconds_of(time: &NaiveTime) -> u32 {
+    time.num_seconds_from_midnight()
+}
+
+fn at<Tz: TimeZone>(tz: &Tz, date: NaiveDate, secs: u32) -> Option<DateTime<Tz>> {
+    tz.from_local_datetime(&date.and_time(time_of_day(secs)))
+        .earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::HourFormat;
+    use crate::registry::RuleSet;
+    use crate::search::Schedule;
+    use crate::significance::is_significant;
+    use chrono::{prelude::*, Duration};
+
+    fn brute_force_next(time: DateTime<Local>) -> DateTime<Local> {
+        let mut candidate = time + Duration::seconds(1);
+        while !is_significant(candidate) {
+            candidate += Duration::seconds(1);
+        }
+        candidate
+    }
+
+    #[test]
+    fn next_matches_brute_force() {
+        let schedule = Schedule::default();
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut time = start;
+        while time < start + Duration::days(1) {
+            let next = schedule.next_after(&time).unwrap();
+            assert_eq!(next, brute_force_next(time));
+            time = next;
+        }
+    }
+
+    #[test]
+    fn next_is_strictly_after() {
+        let schedule = Schedule::default();
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 12, 34, 56).unwrap();
+        assert!(schedule.next_after(&time).unwrap() > time);
+    }
+
+    #[test]
+    fn next_wraps_to_tomorrow() {
+        let schedule = Schedule::default();
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 23, 59, 59).unwrap();
+        assert_eq!(
+            schedule.next_after(&time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn previous_wraps_to_yesterday() {
+        let schedule = Schedule::default();
+        let time = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        assert_eq!(
+            schedule.previous_before(&time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 23, 55, 32).unwrap())
+        );
+    }
+
+    #[test]
+    fn previous_inverts_next() {
+        let schedule = Schedule::default();
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 13, 0, 0).unwrap();
+        let next = schedule.next_after(&time).unwrap();
+        assert_eq!(
+            schedule.next_after(&schedule.previous_before(&next).unwrap()),
+            Some(next)
+        );
+    }
+
+    #[test]
+    fn range_yields_every_significant_time() {
+        let schedule = Schedule::default();
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let end = start + Duration::days(2);
+        let times: Vec<_> = schedule.between(&start, &end).collect();
+        assert_eq!(times.len(), 2 * schedule.len());
+        assert_eq!(times[0], start);
+        assert!(times.iter().all(|time| is_significant(*time)));
+        assert!(times.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn default_schedule_matches_live_evaluation() {
+        // The same rules in another order aren't precomputed, so are evaluated second by second.
+        let reordered = RuleSet::from_names(&["mirror", "run", "all-equal"]).unwrap();
+        assert_eq!(Schedule::default(), Schedule::new(&reordered));
+    }
+
+    #[test]
+    fn twelve_hour_schedule_reads_displayed_hour() {
+        let schedule = Schedule::with_hour_format(&RuleSet::default(), HourFormat::Twelve);
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 12, 0).unwrap();
+        assert_eq!(
+            schedule.next_after(&time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 12, 12).unwrap())
+        );
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 13, 23, 0).unwrap();
+        assert_eq!(
+            schedule.next_after(&time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 13, 23, 45).unwrap())
+        );
+    }
+
+    #[test]
+    fn empty_schedule_never_fires() {
+        let schedule = Schedule::new(&RuleSet::empty());
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(schedule.next_after(&time), None);
+        assert_eq!(schedule.previous_before(&time), None);
+        assert_eq!(
+            schedule.between(&time, &(time + Duration::days(1))).count(),
+            0
+        );
+    }
+}
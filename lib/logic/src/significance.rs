@@ -1,43 +1,132 @@
-use chrono::{DateTime, Local, Timelike};
-
-pub fn is_significant(time: DateTime<Local>) -> bool {
-    let same_start_end = |numbers: &[u8]| numbers[0] == numbers[3] && numbers[2] == numbers[5];
-
-    let same_start_end_reversed =
-        |numbers: &[u8]| numbers[0] == numbers[5] && numbers[2] == numbers[3];
-
-    let internal_pattern = |time: DateTime<Local>| {
-        // TODO use an iterator here not vector
-        let numbers: Vec<u8> = time
-            .format("%H%M%S")
-            .to_string()
-            .chars()
-            .map(|c| c.try_into().expect("Unable to convert '{c}' to u8!"))
-            .collect();
-        let diffs: Vec<i8> = numbers
-            .windows(2)
-            .map(|window| window[1] as i8 - window[0] as i8)
-            .collect();
-        (diffs[0] == -diffs[4]
-            && diffs[1] == -diffs[3]
-            && (same_start_end(&numbers) || same_start_end_reversed(&numbers)))
-            || diffs == vec![1, 1, 1, 1, 1]
-    };
+use chrono::Timelike;
+
+use crate::rules::{best, digits, minute_digits, DEFAULT, MINUTE};
+pub use crate::rules::{Significance, MATCH};
+use crate::table;
+
+// These all read the time as given.  To use a 12-hour clock, pass in
+// `HourFormat::Twelve.displayed(&time)`.
+
+/// Whether `time` matches any rule in the default rule set.
+pub fn is_significant(time: impl Timelike) -> bool {
+    table::DEFAULT.is_significant(&time)
+}
+
+/// The first rule in the default rule set matching `time`, if any.
+pub fn explain(time: impl Timelike) -> Option<Significance<'static>> {
+    if !table::DEFAULT.is_significant(&time) {
+        return None;
+    }
+    let digits = digits(&time);
+    DEFAULT.iter().find_map(|rule| rule.explain(&digits))
+}
+
+/// How interesting `time` is under the default rule set, from 0 to [`MATCH`] for a full match.
+pub fn score(time: impl Timelike) -> u8 {
+    let digits = digits(&time);
+    DEFAULT
+        .iter()
+        .map(|rule| rule.score(&digits))
+        .max()
+        .unwrap_or(0)
+}
 
-    time.minute() == time.hour() && time.hour() == time.second() // 12:12:12
-        || internal_pattern(time) // 12:34:56 || 12:33:21
+/// Like [`explain`], but also accepting near misses scoring at least `threshold`.
+pub fn explain_above(time: impl Timelike, threshold: u8) -> Option<Significance<'static>> {
+    if threshold >= MATCH {
+        return explain(time);
+    }
+    best(DEFAULT.iter().copied(), &digits(&time), threshold)
+}
+
+/// Like [`explain_above`], but for clocks without seconds: only the hours and minutes are read,
+/// against the [`MINUTE`] rules.
+pub fn explain_minute(time: impl Timelike, threshold: u8) -> Option<Significance<'static>> {
+    let digits = minute_digits(&time);
+    if threshold >= MATCH {
+        MINUTE.iter().find_map(|rule| rule.explain(&digits))
+    } else {
+        best(MINUTE.iter().copied(), &digits, threshold)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::fs;
+    use std::{fs, path::Path};
 
-    use crate::significance::is_significant;
+    use crate::format::HourFormat;
+    use crate::rules::FlashStyle;
+    use crate::significance::{
+        explain, explain_above, explain_minute, is_significant, score, Significance, MATCH,
+    };
     use chrono::prelude::*;
 
+    #[test]
+    fn explanation_names_matching_rule() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 12, 34, 56).unwrap();
+        assert_eq!(
+            explain(time),
+            Some(Significance {
+                rule: "run",
+                label: "run",
+                digits: 0b11_1111,
+                score: MATCH,
+                flash: FlashStyle::Single,
+            })
+        );
+    }
+
+    #[test]
+    fn explanation_prefers_earlier_rules() {
+        // Also a palindrome, but all-equal comes first.
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 11, 11, 11).unwrap();
+        assert_eq!(explain(time).unwrap().rule, "all-equal");
+    }
+
+    #[test]
+    fn visibility_orders_near_misses() {
+        let invisible = score(Local.with_ymd_and_hms(2024, 1, 1, 23, 57, 54).unwrap());
+        let barely = score(Local.with_ymd_and_hms(2024, 1, 1, 0, 16, 55).unwrap());
+        let slightly = score(Local.with_ymd_and_hms(2024, 1, 1, 0, 12, 11).unwrap());
+        assert!(invisible < barely && barely < slightly && slightly < MATCH);
+    }
+
+    #[test]
+    fn lower_threshold_is_less_selective() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 12, 11).unwrap();
+        assert_eq!(explain_above(time, MATCH), None);
+        assert_eq!(explain_above(time, 50).unwrap().rule, "mirror");
+    }
+
+    #[test]
+    fn twelve_hour_times_can_be_significant() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 13, 23, 45).unwrap();
+        assert!(!is_significant(time));
+        assert_eq!(
+            explain(HourFormat::Twelve.displayed(&time)).unwrap().rule,
+            "run"
+        );
+    }
+
+    #[test]
+    fn minute_explanation_ignores_seconds() {
+        for s in 0..60 {
+            let time = Local.with_ymd_and_hms(2024, 1, 1, 12, 21, s).unwrap();
+            assert_eq!(explain_minute(time, MATCH).unwrap().rule, "palindrome");
+        }
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 12, 22, 21).unwrap();
+        assert_eq!(explain_minute(time, MATCH), None);
+    }
+
+    #[test]
+    fn insignificant_times_have_no_explanation() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 10, 27, 43).unwrap();
+        assert_eq!(explain(time), None);
+    }
+
     #[test]
     fn ascending_descending_is_significant() {
-        let time = Local.with_ymd_and_hms(2024, 1, 1, 01, 22, 10).unwrap();
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 1, 22, 10).unwrap();
         assert!(is_significant(time))
     }
 
@@ -53,19 +142,27 @@ mod tests {
         assert!(is_significant(time))
     }
 
+    /// The default rule set must keep agreeing with the checked-in `significance.csv`.
     #[test]
-    fn write_csv() {
-        let mut rows = vec![];
+    fn matches_golden_csv() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("significance.csv");
+        let golden = fs::read_to_string(path).unwrap();
+        let mut rows = golden.lines();
         for h in 0..=23 {
             for m in 0..=59 {
                 for s in 0..=59 {
                     let time = Local.with_ymd_and_hms(2024, 1, 1, h, m, s).unwrap();
                     let val = format!("{},{}", time.format("%H:%M:%S"), is_significant(time));
-                    rows.push(val);
+                    assert_eq!(
+                        rows.next(),
+                        Some(val.as_str()),
+                        "significance.csv is out of date; regenerate it with \
+                         `cargo run --bin significance -- export csv > significance.csv`"
+                    );
                 }
             }
         }
-        fs::write("significance.csv", rows.join("\n")).unwrap();
+        assert_eq!(rows.next(), None, "significance.csv has extra rows");
     }
 
     #[test]
@@ -82,13 +179,13 @@ mod tests {
 
     #[test]
     fn barely_visible_pattern_ignored() {
-        let time = Local.with_ymd_and_hms(2024, 1, 1, 00, 16, 55).unwrap();
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 16, 55).unwrap();
         assert!(!is_significant(time));
     }
 
     #[test]
     fn slightly_visible_pattern_ignored() {
-        let time = Local.with_ymd_and_hms(2024, 1, 1, 00, 12, 11).unwrap();
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 12, 11).unwrap();
         assert!(!is_significant(time));
     }
 }
@@ -0,0 +1,137 @@
+//! Sunrise and sunset, from the [sunrise equation](https://en.wikipedia.org/wiki/Sunrise_equation).
+//!
+//! Good to a minute or two away from the poles, which is plenty for dimming a clock.
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+/// Where the clock is, in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Location {
+    /// North is positive.
+    pub latitude: f64,
+    /// East is positive.
+    pub longitude: f64,
+}
+
+/// What the sun does on a given day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sun {
+    /// Rises and sets, at these times (UTC).
+    RisesAndSets {
+        sunrise: NaiveDateTime,
+        sunset: NaiveDateTime,
+    },
+    /// Midnight sun.
+    AlwaysUp,
+    /// Polar night.
+    AlwaysDown,
+}
+
+/// Julian date of the Unix epoch.
+const UNIX_EPOCH: f64 = 2_440_587.5;
+/// Julian date of J2000, noon on 1st January 2000.
+const J2000: f64 = 2_451_545.0;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+fn julian(date: NaiveDate) -> f64 {
+    let days = date - NaiveDate::from_ymd_opt(1970, 1, 1).expect("The epoch is a valid date");
+    days.num_days() as f64 + UNIX_EPOCH
+}
+
+fn from_julian(julian: f64) -> NaiveDateTime {
+    let seconds = ((julian - UNIX_EPOCH) * SECONDS_PER_DAY).round() as i64;
+    DateTime::<Utc>::from_timestamp(seconds, 0)
+        .expect("Sunrise is within chrono's range")
+        .naive_utc()
+}
+
+impl Sun {
+    /// The sun on `date` at `location`.
+    pub fn on(date: NaiveDate, location: Location) -> Sun {
+        // Days since J2000, to the solar noon nearest the meridian at `location`.
+        let day = (julian(date) - J2000 + 0.0008).ceil();
+        let noon = day - location.longitude / 360.0;
+
+        let anomaly = (357.5291 + 0.985_600_28 * noon)
+            .rem_euclid(360.0)
+            .to_radians();
+        let centre = 1.9148 * anomaly.sin()
+            + 0.0200 * (2.0 * anomaly).sin()
+            + 0.0003 * (3.0 * anomaly).sin();
+        let ecliptic = (anomaly.to_degrees() + centre + 180.0 + 102.9372)
+            .rem_euclid(360.0)
+            .to_radians();
+        let transit = J2000 + noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic).sin();
+
+        let declination = (ecliptic.sin() * 23.4397_f64.to_radians().sin()).asin();
+        let latitude = location.latitude.to_radians();
+        // -0.833° allows for refraction and the size of the sun's disc.
+        let cos_hour_angle = ((-0.833_f64).to_radians().sin() - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+        if cos_hour_angle < -1.0 {
+            return Sun::AlwaysUp;
+        }
+        if cos_hour_angle > 1.0 {
+            return Sun::AlwaysDown;
+        }
+        let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+        Sun::RisesAndSets {
+            sunrise: from_julian(transit - half_day),
+            sunset: from_julian(transit + half_day),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use crate::sun::{Location, Sun};
+
+    const LONDON: Location = Location {
+        latitude: 51.5074,
+        longitude: -0.1278,
+    };
+    const TROMSO: Location = Location {
+        latitude: 69.6492,
+        longitude: 18.9553,
+    };
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn near(actual: NaiveDateTime, expected: NaiveDateTime) -> bool {
+        (actual - expected).num_minutes().abs() <= 2
+    }
+
+    #[test]
+    fn london() {
+        // Published times, in UTC.
+        let cases = [
+            ((2023, 6, 21), (3, 43), (20, 21)),
+            ((2023, 12, 21), (8, 4), (15, 54)),
+            ((2024, 3, 20), (6, 2), (18, 14)),
+        ];
+        for ((y, m, d), (rise_h, rise_m), (set_h, set_m)) in cases {
+            let day = date(y, m, d);
+            let Sun::RisesAndSets { sunrise, sunset } = Sun::on(day, LONDON) else {
+                panic!("The sun rises in London on {day}");
+            };
+            let rise = day.and_hms_opt(rise_h, rise_m, 0).unwrap();
+            let set = day.and_hms_opt(set_h, set_m, 0).unwrap();
+            assert!(near(sunrise, rise), "{day}: sunrise at {sunrise}");
+            assert!(near(sunset, set), "{day}: sunset at {sunset}");
+        }
+    }
+
+    #[test]
+    fn polar() {
+        assert_eq!(Sun::on(date(2023, 6, 21), TROMSO), Sun::AlwaysUp);
+        assert_eq!(Sun::on(date(2023, 12, 21), TROMSO), Sun::AlwaysDown);
+        assert!(matches!(
+            Sun::on(date(2023, 3, 20), TROMSO),
+            Sun::RisesAndSets { .. }
+        ));
+    }
+}
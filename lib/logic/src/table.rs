@@ -0,0 +1,68 @@
+//! Precomputed significance, generated at build time by `build.rs`.
+use chrono::Timelike;
+
+pub const SECONDS_PER_DAY: usize = 86_400;
+
+/// One bit per second of the day, set if that second is significant.
+pub struct Table(&'static [u8; SECONDS_PER_DAY / 8]);
+
+/// The default rule set.
+pub static DEFAULT: Table = Table(include_bytes!(concat!(env!("OUT_DIR"), "/default.bin")));
+
+/// Every built-in rule.
+pub static ALL: Table = Table(include_bytes!(concat!(env!("OUT_DIR"), "/all.bin")));
+
+impl Table {
+    /// Whether second `secs` of the day is significant.
+    pub fn contains(&self, secs: u32) -> bool {
+        let secs = secs as usize % SECONDS_PER_DAY;
+        self.0[secs / 8] & (1 << (secs % 8)) != 0
+    }
+
+    pub fn is_significant<T: Timelike>(&self, time: &T) -> bool {
+        self.contains(time.num_seconds_from_midnight())
+    }
+
+    /// Number of significant seconds in a day.
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::registry::RuleSet;
+    use crate::rules::{digits, BUILTIN};
+    use crate::table::{Table, ALL, DEFAULT, SECONDS_PER_DAY};
+    use chrono::NaiveTime;
+
+    fn check(table: &Table, rules: &RuleSet) {
+        for secs in 0..SECONDS_PER_DAY as u32 {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap();
+            assert_eq!(
+                table.contains(secs),
+                rules.matches(&digits(&time)),
+                "Table disagrees with rules at {time}"
+            );
+        }
+    }
+
+    #[test]
+    fn default_table_matches_rules() {
+        check(&DEFAULT, &RuleSet::default());
+    }
+
+    #[test]
+    fn all_table_matches_rules() {
+        check(&ALL, &RuleSet::from_names(BUILTIN).unwrap());
+    }
+
+    #[test]
+    fn all_is_superset_of_default() {
+        assert!(ALL.len() > DEFAULT.len());
+    }
+}
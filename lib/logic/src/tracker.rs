@@ -0,0 +1,150 @@
+//! Turning repeated polling into one event per significant moment.
+//!
+//! The clock redraws several times a second, so the same significant second is seen several
+//! times, and some patterns (like 11:11:xx with a minute-wide rule) hold for many seconds in a row.
+//! A [`Tracker`] reports each moment once, and can hold a rule back for a while after it fires.
+use alloc::{collections::BTreeMap, string::String};
+
+use chrono::{NaiveDateTime, Timelike};
+
+use crate::rules::Significance;
+
+/// How long a rule stays quiet after firing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Cooldown {
+    /// Seconds after any rule fires before it can fire again.  0 only suppresses repeats within
+    /// the same second.
+    pub seconds: u32,
+    /// Overrides of `seconds` for individual rules, by name.
+    pub rules: BTreeMap<String, u32>,
+}
+
+impl Cooldown {
+    pub fn for_rule(&self, rule: &str) -> u32 {
+        self.rules.get(rule).copied().unwrap_or(self.seconds)
+    }
+}
+
+/// Remembers when each rule last fired, to report each significant moment only once.
+#[derive(Clone, Debug, Default)]
+pub struct Tracker {
+    cooldown: Cooldown,
+    fired: BTreeMap<String, NaiveDateTime>,
+}
+
+impl Tracker {
+    pub fn new(cooldown: Cooldown) -> Tracker {
+        Tracker {
+            cooldown,
+            fired: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Cooldown) {
+        self.cooldown = cooldown;
+    }
+
+    /// Record that polling at `now` found `significance`, returning it if it should be announced:
+    /// the first time in this second, and not within its rule's cooldown.
+    pub fn observe<'a>(
+        &mut self,
+        now: NaiveDateTime,
+        significance: Option<Significance<'a>>,
+    ) -> Option<Significance<'a>> {
+        let significance = significance?;
+        let now = now.with_nanosecond(0).expect("Zero nanoseconds are valid");
+        if let Some(last) = self.fired.get(significance.rule) {
+            let elapsed = (now - *last).num_seconds();
+            // A clock set backwards (say by NTP) starts afresh rather than going quiet.
+            let cooling = elapsed < self.cooldown.for_rule(significance.rule).max(1) as i64;
+            if elapsed >= 0 && cooling {
+                return None;
+            }
+        }
+        self.fired.insert(significance.rule.into(), now);
+        Some(significance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::{FlashStyle, Significance, MATCH};
+    use crate::tracker::{Cooldown, Tracker};
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+    fn at(h: u32, m: u32, s: u32, ms: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_milli_opt(h, m, s, ms)
+            .unwrap()
+    }
+
+    fn hit(rule: &str) -> Option<Significance<'_>> {
+        Some(Significance {
+            rule,
+            label: rule,
+            digits: 0b11_1111,
+            score: MATCH,
+            flash: FlashStyle::Double,
+        })
+    }
+
+    /// Poll every 100ms from `start` for `seconds`, with `rule` matching throughout, returning
+    /// how many events fire.
+    fn poll(tracker: &mut Tracker, start: NaiveDateTime, seconds: i64, rule: &str) -> usize {
+        (0..seconds * 10)
+            .map(|tick| start + Duration::milliseconds(tick * 100))
+            .filter(|now| tracker.observe(*now, hit(rule)).is_some())
+            .count()
+    }
+
+    #[test]
+    fn one_event_per_significant_second() {
+        let mut tracker = Tracker::default();
+        assert_eq!(poll(&mut tracker, at(12, 34, 56, 0), 1, "run"), 1);
+        assert_eq!(tracker.observe(at(12, 34, 56, 900), hit("run")), None);
+        assert!(tracker.observe(at(12, 34, 57, 0), hit("run")).is_some());
+    }
+
+    #[test]
+    fn nothing_to_report_without_significance() {
+        let mut tracker = Tracker::default();
+        assert_eq!(tracker.observe(at(12, 0, 0, 0), None), None);
+    }
+
+    #[test]
+    fn cooldown_merges_bursts() {
+        let cooldown = Cooldown {
+            seconds: 60,
+            ..Cooldown::default()
+        };
+        let mut tracker = Tracker::new(cooldown);
+        assert_eq!(poll(&mut tracker, at(11, 11, 0, 0), 60, "eleven"), 1);
+        assert_eq!(poll(&mut tracker, at(11, 12, 0, 0), 1, "eleven"), 1);
+    }
+
+    #[test]
+    fn cooldown_is_per_rule() {
+        let mut rules = Cooldown::default().rules;
+        rules.insert("pairs".into(), 10);
+        let mut tracker = Tracker::new(Cooldown { seconds: 0, rules });
+        assert_eq!(poll(&mut tracker, at(11, 22, 33, 0), 5, "pairs"), 1);
+        assert_eq!(poll(&mut tracker, at(11, 22, 33, 0), 5, "mirror"), 5);
+    }
+
+    #[test]
+    fn clock_going_backwards_restarts_cooldown() {
+        let mut tracker = Tracker::new(Cooldown {
+            seconds: 3600,
+            ..Cooldown::default()
+        });
+        assert!(tracker.observe(at(12, 0, 0, 0), hit("run")).is_some());
+        assert!(tracker.observe(at(11, 0, 0, 0), hit("run")).is_some());
+        assert!(tracker.observe(at(11, 30, 0, 0), hit("run")).is_none());
+    }
+}
@@ -0,0 +1,199 @@
+//! Compile the fonts and icons in `fonts/` into the glyph tables included by `src/font.rs`.
+//!
+//! Each `<name>.bdf` becomes a `Font` static called `<NAME>`.  Each `<name>.png` is a sheet of
+//! square icons as high as the image, named in order by the lines of `<name>.txt` beside it, and
+//! each becomes a `Glyph` static called `<NAME>_<ICON>`; light pixels are lit.
+use std::{
+    env,
+    fmt::Write,
+    fs::{self, File},
+    path::Path,
+};
+
+/// Glyphs are stored a column to a `u16`.
+const MAX_HEIGHT: u32 = 16;
+
+struct Glyph {
+    advance: u32,
+    /// Lit pixels in each column, the top row in the lowest bit.
+    columns: Vec<u16>,
+}
+
+impl Glyph {
+    fn source(&self) -> String {
+        format!(
+            "Glyph {{ advance: {}, columns: &{:?} }}",
+            self.advance, self.columns
+        )
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=fonts");
+    let mut paths: Vec<_> = fs::read_dir("fonts")
+        .expect("fonts/ is readable")
+        .map(|entry| entry.expect("fonts/ is readable").path())
+        .collect();
+    paths.sort();
+
+    let mut out = String::new();
+    for path in &paths {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .expect("Font names are UTF-8")
+            .to_uppercase()
+            .replace('-', "_");
+        let file = path.display();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("bdf") => {
+                let source = fs::read_to_string(path).expect("BDF fonts are readable");
+                let (height, glyphs) = parse_bdf(&source).unwrap_or_else(|e| panic!("{file}: {e}"));
+                let _ = writeln!(out, "/// Built from `{file}`.");
+                let _ = writeln!(
+                    out,
+                    "pub static {name}: Font = Font {{ height: {height}, glyphs: &["
+                );
+                for (c, glyph) in glyphs {
+                    let _ = writeln!(out, "    ({c:?}, {}),", glyph.source());
+                }
+                let _ = writeln!(out, "] }};");
+            }
+            Some("png") => {
+                let names = fs::read_to_string(path.with_extension("txt"))
+                    .unwrap_or_else(|e| panic!("{file} needs its icons named in a .txt: {e}"));
+                let names: Vec<&str> = names.lines().map(str::trim).collect();
+                let icons = parse_png(path, names.len()).unwrap_or_else(|e| panic!("{file}: {e}"));
+                for (icon, glyph) in names.iter().zip(icons) {
+                    let _ = writeln!(out, "/// `{icon}` from `{file}`.");
+                    let _ = writeln!(
+                        out,
+                        "static {name}_{}: Glyph = {};",
+                        icon.to_uppercase().replace('-', "_"),
+                        glyph.source()
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let dest = Path::new(&env::var("OUT_DIR").expect("Cargo sets OUT_DIR")).join("fonts.rs");
+    fs::write(dest, out).expect("OUT_DIR is writable");
+}
+
+/// Parse a BDF font into its height and glyphs, sorted by character.  Glyphs are placed on the
+/// baseline `FONT_ASCENT` rows down, and their advance is their `DWIDTH`.
+fn parse_bdf(source: &str) -> Result<(u32, Vec<(char, Glyph)>), String> {
+    let (mut ascent, mut descent) = (None, 0);
+    let mut glyphs = Vec::new();
+    let mut encoding = None;
+    let mut advance = 0;
+    // Width, height, and x and y offsets from the origin on the baseline.
+    let mut bbx = (0, 0, 0, 0);
+    let mut lines = source.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let numbers: Vec<i32> = words.filter_map(|word| word.parse().ok()).collect();
+        let number = |i: usize| {
+            numbers
+                .get(i)
+                .copied()
+                .ok_or_else(|| format!("'{line}' is missing a number"))
+        };
+        match keyword {
+            "FONT_ASCENT" => ascent = Some(number(0)?),
+            "FONT_DESCENT" => descent = number(0)?,
+            "ENCODING" => encoding = char::from_u32(number(0)? as u32),
+            "DWIDTH" => advance = number(0)?,
+            "BBX" => bbx = (number(0)?, number(1)?, number(2)?, number(3)?),
+            "BITMAP" => {
+                let ascent = ascent.ok_or("FONT_ASCENT must come before the glyphs")?;
+                let height = (ascent + descent) as u32;
+                if height > MAX_HEIGHT {
+                    return Err(format!("Fonts can be at most {MAX_HEIGHT} pixels high"));
+                }
+                let (width, rows, x_offset, y_offset) = bbx;
+                let mut columns = vec![0; (x_offset + width).max(0) as usize];
+                let top = ascent - (y_offset + rows);
+                for row in 0..rows {
+                    let hex = lines.next().ok_or("Bitmap ends early")?;
+                    if hex.is_empty() || hex.len() > 8 {
+                        return Err(format!("Bitmap row '{hex}' isn't 1 to 32 pixels wide"));
+                    }
+                    let bits = u32::from_str_radix(hex, 16)
+                        .map_err(|e| format!("Bad bitmap row '{hex}': {e}"))?;
+                    let bits = bits << (32 - hex.len() * 4);
+                    for col in 0..width {
+                        if bits & (1 << (31 - col)) == 0 {
+                            continue;
+                        }
+                        let (x, y) = (x_offset + col, top + row);
+                        if x < 0 || y < 0 || y as u32 >= height {
+                            return Err(format!("{encoding:?} has a pixel off the font"));
+                        }
+                        columns[x as usize] |= 1 << y;
+                    }
+                }
+                if let Some(c) = encoding {
+                    glyphs.push((
+                        c,
+                        Glyph {
+                            advance: advance.max(0) as u32,
+                            columns,
+                        },
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+    let height = ascent.ok_or("No FONT_ASCENT")? + descent;
+    glyphs.sort_by_key(|(c, _)| *c);
+    Ok((height as u32, glyphs))
+}
+
+/// Cut a PNG sheet into `count` square icons.
+fn parse_png(path: &Path, count: usize) -> Result<Vec<Glyph>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+    let size = info.height;
+    if size > MAX_HEIGHT {
+        return Err(format!("Icons can be at most {MAX_HEIGHT} pixels high"));
+    }
+    if info.width != size * count as u32 {
+        return Err(format!("Expected {count} {size}x{size} icons side by side"));
+    }
+    let samples = info.color_type.samples();
+    let has_alpha = matches!(
+        info.color_type,
+        png::ColorType::GrayscaleAlpha | png::ColorType::Rgba
+    );
+    let lit = |x: u32, y: u32| {
+        let i = (y * info.width + x) as usize * samples;
+        let pixel = &data[i..i + samples];
+        let (colour, alpha) = match has_alpha {
+            true => (&pixel[..samples - 1], pixel[samples - 1]),
+            false => (pixel, u8::MAX),
+        };
+        let brightness = colour.iter().map(|c| *c as u32).sum::<u32>() / colour.len() as u32;
+        alpha >= 128 && brightness >= 128
+    };
+    Ok((0..count as u32)
+        .map(|icon| Glyph {
+            advance: size,
+            columns: (0..size)
+                .map(|x| {
+                    (0..size)
+                        .filter(|y| lit(icon * size + x, *y))
+                        .fold(0, |column, y| column | 1 << y)
+                })
+                .collect(),
+        })
+        .collect())
+}
@@ -0,0 +1,433 @@
+//! Animations as functions of time, rendered frame by frame into [`Bitmap`]s.
+//!
+//! Nothing here keeps time: the caller asks for the frame at however long it's been since the
+//! start, as often as its display can manage, so frame rates and polling loops stay independent.
+use alloc::{boxed::Box, vec::Vec};
+use core::time::Duration;
+
+use embedded_graphics::prelude::*;
+
+use crate::bitmap::Bitmap;
+
+pub trait Animation {
+    fn length(&self) -> Duration;
+
+    /// The frame `elapsed` after the start.  Past the end this is the last frame.
+    fn frame(&self, elapsed: Duration) -> Bitmap;
+}
+
+/// Which way things move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// How one image gives way to the next.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Effect {
+    /// Straight to the new image.
+    #[default]
+    Cut,
+    /// The new image is uncovered by an edge moving in the given direction.
+    Wipe(Direction),
+    /// The old image is pushed out by the new one, moving in the given direction.
+    Slide(Direction),
+    /// Pixels change over one at a time, in a fixed scattered order.
+    Dissolve,
+}
+
+/// How far through `length` we are at `elapsed`, out of `scale`.
+fn progress(elapsed: Duration, length: Duration, scale: u32) -> u32 {
+    if elapsed >= length {
+        return scale;
+    }
+    (elapsed.as_micros() * scale as u128 / length.as_micros()) as u32
+}
+
+/// A scattered but repeatable rank for each pixel, below 256.
+fn scatter(x: u32, y: u32) -> u32 {
+    let mut hash = x.wrapping_mul(0x9E37_79B9) ^ y.wrapping_mul(0x85EB_CA6B);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2C1B_3C6D);
+    hash ^= hash >> 12;
+    hash & 0xFF
+}
+
+/// A change from one image to another of the same size.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub effect: Effect,
+    pub from: Bitmap,
+    pub to: Bitmap,
+    pub length: Duration,
+}
+
+impl Animation for Transition {
+    fn length(&self) -> Duration {
+        self.length
+    }
+
+    fn frame(&self, elapsed: Duration) -> Bitmap {
+        let Size { width, height } = self.to.size();
+        let mut frame = Bitmap::new(width, height);
+        let span = |direction| match direction {
+            Direction::Left | Direction::Right => width,
+            Direction::Up | Direction::Down => height,
+        };
+        for (x, y) in frame.coordinates() {
+            let point = Point::new(x as i32, y as i32);
+            let on = match self.effect {
+                Effect::Cut => self.to.get(x, y),
+                Effect::Wipe(direction) => {
+                    let edge = progress(elapsed, self.length, span(direction));
+                    let uncovered = match direction {
+                        Direction::Right => x < edge,
+                        Direction::Left => x >= width - edge,
+                        Direction::Down => y < edge,
+                        Direction::Up => y >= height - edge,
+                    };
+                    match uncovered {
+                        true => self.to.get(x, y),
+                        false => self.from.get(x, y),
+                    }
+                }
+                Effect::Slide(direction) => {
+                    let moved = progress(elapsed, self.length, span(direction)) as i32;
+                    let (step, span) = match direction {
+                        Direction::Right => (Point::new(-1, 0), width as i32),
+                        Direction::Left => (Point::new(1, 0), width as i32),
+                        Direction::Down => (Point::new(0, -1), height as i32),
+                        Direction::Up => (Point::new(0, 1), height as i32),
+                    };
+                    // Follow the motion back to where this pixel came from.
+                    let old = point + step * moved;
+                    match self.from.bounding_box().contains(old) {
+                        true => self.from.at(old),
+                        false => self.to.at(old - step * span),
+                    }
+                }
+                Effect::Dissolve => match scatter(x, y) < progress(elapsed, self.length, 256) {
+                    true => self.to.get(x, y),
+                    false => self.from.get(x, y),
+                },
+            };
+            frame.set(x, y, on);
+        }
+        frame
+    }
+}
+
+/// Equal-sized sprites laid out left to right, top to bottom, on one image.
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    sheet: Bitmap,
+    sprite: Size,
+}
+
+impl SpriteSheet {
+    pub fn new(sheet: Bitmap, sprite: Size) -> SpriteSheet {
+        SpriteSheet { sheet, sprite }
+    }
+
+    fn columns(&self) -> u32 {
+        self.sheet.width() / self.sprite.width.max(1)
+    }
+
+    pub fn len(&self) -> usize {
+        (self.columns() * (self.sheet.height() / self.sprite.height.max(1))) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn sprite_size(&self) -> Size {
+        self.sprite
+    }
+
+    /// Sprite `index`, or a blank one past the end of the sheet.
+    pub fn sprite(&self, index: usize) -> Bitmap {
+        if index >= self.len() {
+            return Bitmap::new(self.sprite.width, self.sprite.height);
+        }
+        let (col, row) = (index as u32 % self.columns(), index as u32 / self.columns());
+        let origin = Point::new(
+            (col * self.sprite.width) as i32,
+            (row * self.sprite.height) as i32,
+        );
+        self.sheet.crop(origin, self.sprite)
+    }
+}
+
+/// A sprite shown at a point in time.  Between keyframes the sprite stays the same and its
+/// position moves in a straight line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keyframe {
+    pub at: Duration,
+    pub sprite: usize,
+    /// Where the sprite's top left goes.
+    pub position: Point,
+}
+
+/// Sprites from a sheet, keyframed over a background.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    background: Bitmap,
+    sprites: SpriteSheet,
+    keyframes: Vec<Keyframe>,
+    repeats: u32,
+}
+
+impl Sequence {
+    /// Keyframes are sorted by time; the last one marks the end of each repeat.
+    pub fn new(background: Bitmap, sprites: SpriteSheet, mut keyframes: Vec<Keyframe>) -> Sequence {
+        keyframes.sort_by_key(|keyframe| keyframe.at);
+        Sequence {
+            background,
+            sprites,
+            keyframes,
+            repeats: 1,
+        }
+    }
+
+    /// Play through `repeats` times.
+    pub fn repeat(self, repeats: u32) -> Sequence {
+        Sequence {
+            repeats: repeats.max(1),
+            ..self
+        }
+    }
+
+    fn period(&self) -> Duration {
+        self.keyframes.last().map_or(Duration::ZERO, |last| last.at)
+    }
+
+    /// The sprite and where it goes `elapsed` into one repeat.
+    fn at(&self, elapsed: Duration) -> Option<(usize, Point)> {
+        let next = self.keyframes.iter().position(|k| k.at > elapsed);
+        let (from, to) = match next {
+            Some(0) => return None,
+            Some(next) => (&self.keyframes[next - 1], &self.keyframes[next]),
+            None => {
+                let last = self.keyframes.last()?;
+                (last, last)
+            }
+        };
+        if from == to {
+            return Some((from.sprite, from.position));
+        }
+        let (span, into) = (
+            (to.at - from.at).as_micros(),
+            (elapsed - from.at).as_micros(),
+        );
+        let lerp = |a: i32, b: i32| a + ((b - a) as i128 * into as i128 / span as i128) as i32;
+        let position = Point::new(
+            lerp(from.position.x, to.position.x),
+            lerp(from.position.y, to.position.y),
+        );
+        Some((from.sprite, position))
+    }
+}
+
+impl Animation for Sequence {
+    fn length(&self) -> Duration {
+        self.period() * self.repeats
+    }
+
+    fn frame(&self, elapsed: Duration) -> Bitmap {
+        let period = self.period();
+        let elapsed = match elapsed < self.length() && !period.is_zero() {
+            true => Duration::from_nanos((elapsed.as_nanos() % period.as_nanos()) as u64),
+            false => period,
+        };
+        let mut frame = self.background.clone();
+        if let Some((sprite, position)) = self.at(elapsed) {
+            frame.overlay(&self.sprites.sprite(sprite), position);
+        }
+        frame
+    }
+}
+
+/// Several animations, one after another.
+pub struct Chained(pub Vec<Box<dyn Animation + Send>>);
+
+impl Animation for Chained {
+    fn length(&self) -> Duration {
+        self.0.iter().map(|animation| animation.length()).sum()
+    }
+
+    fn frame(&self, mut elapsed: Duration) -> Bitmap {
+        let Some((last, rest)) = self.0.split_last() else {
+            return Bitmap::new(0, 0);
+        };
+        for animation in rest {
+            match elapsed.checked_sub(animation.length()) {
+                Some(remaining) => elapsed = remaining,
+                None => return animation.frame(elapsed),
+            }
+        }
+        last.frame(elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::*;
+    use crate::bitmap::Bitmap;
+    use alloc::{boxed::Box, vec};
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn art(ascii: &str) -> Bitmap {
+        Bitmap::from_ascii(ascii).unwrap()
+    }
+
+    fn transition(effect: Effect) -> Transition {
+        Transition {
+            effect,
+            from: art(&"####\n".repeat(4)),
+            to: Bitmap::new(4, 4),
+            length: ms(400),
+        }
+    }
+
+    #[test]
+    fn transitions_go_from_one_image_to_the_other() {
+        let effects = [
+            Effect::Cut,
+            Effect::Wipe(Direction::Left),
+            Effect::Wipe(Direction::Down),
+            Effect::Slide(Direction::Right),
+            Effect::Slide(Direction::Up),
+            Effect::Dissolve,
+        ];
+        for effect in effects {
+            let transition = transition(effect);
+            if effect != Effect::Cut {
+                assert_eq!(transition.frame(ms(0)), transition.from, "{effect:?}");
+            }
+            assert_eq!(transition.frame(ms(400)), transition.to, "{effect:?}");
+            assert_eq!(transition.frame(ms(1000)), transition.to, "{effect:?}");
+        }
+    }
+
+    #[test]
+    fn wipes_and_slides_move_the_right_way() {
+        let frame = |effect| transition(effect).frame(ms(100)).to_ascii();
+        assert_eq!(frame(Effect::Wipe(Direction::Right)), ".###\n".repeat(4));
+        assert_eq!(frame(Effect::Wipe(Direction::Left)), "###.\n".repeat(4));
+        assert_eq!(
+            frame(Effect::Wipe(Direction::Up)),
+            "####\n####\n####\n....\n"
+        );
+        let stripe = Transition {
+            from: art(".#..\n.#..\n.#..\n.#.."),
+            to: art("...#\n...#\n...#\n...#"),
+            ..transition(Effect::Slide(Direction::Right))
+        };
+        // The stripe moves right one, and the new image's left column follows it in.
+        assert_eq!(stripe.frame(ms(100)).to_ascii(), "#.#.\n".repeat(4));
+        let stripe = Transition {
+            effect: Effect::Slide(Direction::Left),
+            ..stripe
+        };
+        assert_eq!(stripe.frame(ms(100)).to_ascii(), "#...\n".repeat(4));
+    }
+
+    #[test]
+    fn dissolves_change_pixels_steadily() {
+        let big = Transition {
+            effect: Effect::Dissolve,
+            from: Bitmap::new(32, 16).inverted(),
+            to: Bitmap::new(32, 16),
+            length: ms(1000),
+        };
+        let lit = |elapsed| {
+            let frame = big.frame(ms(elapsed));
+            frame
+                .coordinates()
+                .filter(|(x, y)| frame.get(*x, *y))
+                .count()
+        };
+        let mut last = lit(0);
+        assert_eq!(last, 512);
+        for elapsed in (100..=1000).step_by(100) {
+            let now = lit(elapsed);
+            assert!(now < last, "{now} pixels lit at {elapsed}ms, {last} before");
+            last = now;
+        }
+        assert!((200..312).contains(&lit(500)), "{} lit halfway", lit(500));
+    }
+
+    #[test]
+    fn sprites_come_from_the_sheet_in_reading_order() {
+        let sheet = SpriteSheet::new(art("#..#\n....\n.##.\n#..."), Size::new(2, 2));
+        assert_eq!(sheet.len(), 4);
+        assert_eq!(sheet.sprite(0), art("#.\n.."));
+        assert_eq!(sheet.sprite(1), art(".#\n.."));
+        assert_eq!(sheet.sprite(2), art(".#\n#."));
+        assert_eq!(sheet.sprite(3), art("#.\n.."));
+        assert_eq!(sheet.sprite(4), Bitmap::new(2, 2));
+    }
+
+    #[test]
+    fn sequences_move_sprites_between_keyframes() {
+        let dot = SpriteSheet::new(art("#"), Size::new(1, 1));
+        let keyframes = vec![
+            Keyframe {
+                at: ms(0),
+                sprite: 0,
+                position: Point::new(0, 0),
+            },
+            Keyframe {
+                at: ms(300),
+                sprite: 0,
+                position: Point::new(3, 0),
+            },
+        ];
+        let sequence = Sequence::new(Bitmap::new(4, 1), dot, keyframes).repeat(2);
+        assert_eq!(sequence.length(), ms(600));
+        let frame = |elapsed| sequence.frame(ms(elapsed)).to_ascii();
+        assert_eq!(frame(0), "#...\n");
+        assert_eq!(frame(100), ".#..\n");
+        assert_eq!(frame(250), "..#.\n");
+        assert_eq!(frame(300), "#...\n", "second repeat");
+        assert_eq!(frame(600), "...#\n", "held at the end");
+    }
+
+    #[test]
+    fn chained_animations_play_in_turn() {
+        let blank = Bitmap::new(1, 1);
+        let step = |from: &Bitmap, to: &Bitmap| -> Box<dyn Animation + Send> {
+            Box::new(Transition {
+                effect: Effect::Cut,
+                from: from.clone(),
+                to: to.clone(),
+                length: ms(100),
+            })
+        };
+        let chained = Chained(vec![
+            step(&blank, &blank.inverted()),
+            step(&blank.inverted(), &blank),
+        ]);
+        assert_eq!(chained.length(), ms(200));
+        assert_eq!(chained.frame(ms(50)), blank.inverted());
+        assert_eq!(chained.frame(ms(150)), blank);
+        assert_eq!(chained.frame(ms(250)), blank);
+    }
+}
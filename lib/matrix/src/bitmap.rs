@@ -0,0 +1,294 @@
+//! Plain 1-bit images: what a screen shows, for snapshots and previews, and offscreen frames and
+//! sprites for animation.
+use alloc::{string::String, vec, vec::Vec};
+use core::{
+    convert::Infallible,
+    fmt::{self, Write},
+};
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+
+use crate::layout::ScreenConfig;
+
+/// A 1-bit image, which can also be drawn on.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    width: u32,
+    height: u32,
+    pixels: Vec<bool>,
+}
+
+impl Bitmap {
+    /// A blank image.
+    pub fn new(width: u32, height: u32) -> Bitmap {
+        Bitmap {
+            width,
+            height,
+            pixels: vec![false; (width * height) as usize],
+        }
+    }
+
+    /// Parse [`Bitmap::to_ascii`]'s format: rows of `#` (on) and `.` (off), all the same length.
+    /// Blank lines and surrounding whitespace are ignored, so art can be indented in source.
+    pub fn from_ascii(ascii: &str) -> Option<Bitmap> {
+        let rows: Vec<&str> = ascii
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect();
+        let width = rows.first()?.len();
+        let mut pixels = Vec::with_capacity(width * rows.len());
+        for row in &rows {
+            if row.len() != width {
+                return None;
+            }
+            for c in row.chars() {
+                pixels.push(match c {
+                    '#' => true,
+                    '.' => false,
+                    _ => return None,
+                });
+            }
+        }
+        Some(Bitmap {
+            width: width as u32,
+            height: rows.len() as u32,
+            pixels,
+        })
+    }
+
+    /// The logical image held in a framebuffer laid out by `config`.
+    pub(crate) fn from_framebuffer(config: &ScreenConfig, framebuffer: &[u8]) -> Bitmap {
+        let (width, height) = (config.cols(), config.rows());
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| match config.position(x, y) {
+                Some((row, mask)) => framebuffer[row] & mask != 0,
+                None => false,
+            })
+            .collect();
+        Bitmap {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether the pixel at (x, y) is on.  Pixels off the image are off.
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Set the pixel at (x, y), if it's on the image.
+    pub fn set(&mut self, x: u32, y: u32, on: bool) {
+        if x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize] = on;
+        }
+    }
+
+    /// Like [`Bitmap::get`], but for any point, so images can be offset without overflow.
+    pub fn at(&self, point: Point) -> bool {
+        match point.try_into() {
+            Ok((x, y)) => self.get(x, y),
+            Err(_) => false,
+        }
+    }
+
+    /// The `size` region with its top left at `origin`, as a new image.
+    pub fn crop(&self, origin: Point, size: Size) -> Bitmap {
+        let mut cropped = Bitmap::new(size.width, size.height);
+        for (x, y) in cropped.coordinates() {
+            cropped.set(x, y, self.at(origin + Point::new(x as i32, y as i32)));
+        }
+        cropped
+    }
+
+    /// Every (x, y) on the image, row by row.
+    pub fn coordinates(&self) -> impl Iterator<Item = (u32, u32)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    /// Turn on every pixel that's on in `other`, placed with its top left at `origin`.
+    pub fn overlay(&mut self, other: &Bitmap, origin: Point) {
+        for (x, y) in other.coordinates() {
+            if other.get(x, y) {
+                let Point { x, y } = origin + Point::new(x as i32, y as i32);
+                if let (Ok(x), Ok(y)) = (x.try_into(), y.try_into()) {
+                    self.set(x, y, true);
+                }
+            }
+        }
+    }
+
+    /// Every pixel flipped.
+    pub fn inverted(&self) -> Bitmap {
+        Bitmap {
+            pixels: self.pixels.iter().map(|on| !on).collect(),
+            ..self.clone()
+        }
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.pixels.chunks(self.width as usize)
+    }
+
+    /// One line per row, `#` for on and `.` for off.
+    pub fn to_ascii(&self) -> String {
+        let mut ascii = String::with_capacity(((self.width + 1) * self.height) as usize);
+        for row in self.rows() {
+            ascii.extend(row.iter().map(|on| if *on { '#' } else { '.' }));
+            ascii.push('\n');
+        }
+        ascii
+    }
+
+    /// A plain (`P1`) portable bitmap.
+    pub fn to_pbm(&self) -> String {
+        let mut pbm = String::new();
+        // Writing to a String can't fail.
+        let _ = writeln!(pbm, "P1\n{} {}", self.width, self.height);
+        for row in self.rows() {
+            let bits: Vec<&str> = row.iter().map(|on| if *on { "1" } else { "0" }).collect();
+            pbm.push_str(&bits.join(" "));
+            pbm.push('\n');
+        }
+        pbm
+    }
+
+    /// A greyscale PNG, lit pixels white on black, `scale` screen pixels to each LED.
+    #[cfg(feature = "png")]
+    pub fn to_png(&self, scale: u32) -> Result<Vec<u8>, png::EncodingError> {
+        let scale = scale.max(1);
+        let (width, height) = (self.width * scale, self.height * scale);
+        let data: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x / scale, y / scale)))
+            .map(|(x, y)| if self.get(x, y) { u8::MAX } else { 0 })
+            .collect();
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(png)
+    }
+}
+
+impl OriginDimensions for Bitmap {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl DrawTarget for Bitmap {
+    type Error = Infallible;
+    type Color = BinaryColor;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if let Ok((x, y)) = coord.try_into() {
+                self.set(x, y, color.is_on());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Bitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ascii())
+    }
+}
+
+impl fmt::Debug for Bitmap {
+    /// The image itself, so failed comparisons show what differs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Bitmap {}x{}", self.width, self.height)?;
+        f.write_str(&self.to_ascii())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::{ScreenConfig, Segment};
+    use crate::mock;
+    use crate::screen::{Screen, ScreenBuilder};
+    use alloc::vec;
+
+    /// A 16x8 screen of two modules, the right one upside down and first in the chain.
+    fn screen() -> Screen<mock::MockConnector> {
+        let config =
+            ScreenConfig::new(vec![vec![Segment::normal(1), Segment::inverted(0)]]).unwrap();
+        let (display, _) = mock::display(2);
+        ScreenBuilder::new(config).build(display).unwrap()
+    }
+
+    #[test]
+    fn bitmap_is_in_screen_coordinates() {
+        let mut screen = screen();
+        screen.blit(0, 0, true);
+        screen.blit(9, 1, true);
+        screen.blit(15, 7, true);
+        let bitmap = screen.bitmap();
+        assert_eq!((bitmap.width(), bitmap.height()), (16, 8));
+        assert!(bitmap.get(0, 0) && bitmap.get(9, 1) && bitmap.get(15, 7));
+        assert!(!bitmap.get(1, 0) && !bitmap.get(16, 0));
+        assert_eq!(
+            bitmap.to_ascii(),
+            "#...............\n\
+             .........#......\n\
+             ................\n\
+             ................\n\
+             ................\n\
+             ................\n\
+             ................\n\
+             ...............#\n"
+        );
+    }
+
+    #[test]
+    fn pbm_lists_every_pixel() {
+        let mut screen = screen();
+        screen.blit(2, 0, true);
+        let pbm = screen.bitmap().to_pbm();
+        let mut lines = pbm.lines();
+        assert_eq!(lines.next(), Some("P1"));
+        assert_eq!(lines.next(), Some("16 8"));
+        assert_eq!(
+            lines.next(),
+            Some("0 0 1 0 0 0 0 0 0 0 0 0 0 0 0 0"),
+            "first row"
+        );
+        assert_eq!(lines.count(), 7);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_decodes_to_the_same_image() {
+        let mut screen = screen();
+        screen.blit(3, 4, true);
+        let bitmap = screen.bitmap();
+        let png = bitmap.to_png(2).unwrap();
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (32, 16));
+        for y in 0..16 {
+            for x in 0..32 {
+                let on = data[(y * 32 + x) as usize] == u8::MAX;
+                assert_eq!(on, bitmap.get(x / 2, y / 2), "({x}, {y})");
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Bitmap fonts and icons made for the matrix.
+//!
+//! They're compiled from `fonts/` by the build script: BDF files for fonts, and PNG sheets for
+//! icons.  Glyphs are stored as columns of up to 16 pixels, so text can fill one row of modules
+//! ([`SMALL`]) or span two ([`TALL`]).
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+
+use crate::bitmap::Bitmap;
+
+/// One character or icon.
+#[derive(Debug)]
+pub struct Glyph {
+    /// How far right the next glyph starts.
+    advance: u32,
+    /// Lit pixels in each column, the top row in the lowest bit.
+    columns: &'static [u16],
+}
+
+impl Glyph {
+    /// How wide the lit part is, which may be less than the advance.
+    pub fn width(&self) -> u32 {
+        self.columns.len() as u32
+    }
+
+    pub fn advance(&self) -> u32 {
+        self.advance
+    }
+
+    /// Draw with the top left at `origin`.  Unlit pixels are left alone.
+    pub fn draw<D>(&self, origin: Point, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let pixels = self.columns.iter().enumerate().flat_map(|(x, column)| {
+            (0..u16::BITS as i32)
+                .filter(move |y| (column >> y) & 1 == 1)
+                .map(move |y| Pixel(origin + Point::new(x as i32, y), BinaryColor::On))
+        });
+        target.draw_iter(pixels)
+    }
+}
+
+/// A proportional font: each glyph is only as wide as it needs to be.
+#[derive(Debug)]
+pub struct Font {
+    height: u32,
+    /// Sorted by character.
+    glyphs: &'static [(char, Glyph)],
+}
+
+impl Font {
+    /// Rows from the top of a line to the top of the next.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs
+            .binary_search_by_key(&c, |(c, _)| *c)
+            .ok()
+            .map(|i| &self.glyphs[i].1)
+    }
+
+    /// From the left of `text`'s first glyph to the right of its last.  Like
+    /// [`Font::draw`], this skips characters the font doesn't have.
+    pub fn width(&self, text: &str) -> u32 {
+        let mut glyphs = text.chars().filter_map(|c| self.glyph(c)).peekable();
+        let mut width = 0;
+        while let Some(glyph) = glyphs.next() {
+            width += match glyphs.peek() {
+                Some(_) => glyph.advance,
+                None => glyph.width(),
+            };
+        }
+        width
+    }
+
+    /// Draw `text` with its top left at `origin`, returning where the next character would go.
+    pub fn draw<D>(&self, text: &str, origin: Point, target: &mut D) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let mut position = origin;
+        for glyph in text.chars().filter_map(|c| self.glyph(c)) {
+            glyph.draw(position, target)?;
+            position.x += glyph.advance as i32;
+        }
+        Ok(position)
+    }
+
+    /// `text` on its own, exactly as wide as it is and as high as the font.
+    pub fn bitmap(&self, text: &str) -> Bitmap {
+        let mut bitmap = Bitmap::new(self.width(text), self.height);
+        // Drawing on a bitmap can't fail.
+        let _ = self.draw(text, Point::zero(), &mut bitmap);
+        bitmap
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/fonts.rs"));
+
+/// Status icons, 8x8 to fit a module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Icon {
+    Wifi,
+    Alarm,
+    Sync,
+}
+
+impl Icon {
+    pub const SIZE: u32 = 8;
+
+    pub fn glyph(self) -> &'static Glyph {
+        match self {
+            Icon::Wifi => &ICONS_WIFI,
+            Icon::Alarm => &ICONS_ALARM,
+            Icon::Sync => &ICONS_SYNC,
+        }
+    }
+
+    /// Draw with the top left at `origin`.  Unlit pixels are left alone.
+    pub fn draw<D>(self, origin: Point, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        self.glyph().draw(origin, target)
+    }
+}
+
+/// Tiny 4x4 icons, to tuck into the corners of the screen over whatever else is showing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Badge {
+    Wifi,
+    AccessPoint,
+    Sync,
+    Alarm,
+    Star,
+}
+
+impl Badge {
+    pub const SIZE: u32 = 4;
+
+    pub fn glyph(self) -> &'static Glyph {
+        match self {
+            Badge::Wifi => &BADGES_WIFI,
+            Badge::AccessPoint => &BADGES_ACCESS_POINT,
+            Badge::Sync => &BADGES_SYNC,
+            Badge::Alarm => &BADGES_ALARM,
+            Badge::Star => &BADGES_STAR,
+        }
+    }
+
+    /// Draw with the top left at `origin`.  Unlit pixels are left alone.
+    pub fn draw<D>(self, origin: Point, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        self.glyph().draw(origin, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::prelude::*;
+
+    use crate::bitmap::Bitmap;
+    use crate::font::{Badge, Icon, SMALL, TALL};
+
+    #[test]
+    fn small_digits_are_proportional() {
+        assert_eq!(SMALL.height(), 8);
+        assert_eq!(SMALL.glyph('0').unwrap().advance(), 5);
+        assert_eq!(SMALL.glyph('1').unwrap().advance(), 4);
+        // 1, a gap, :, a gap, then 0.
+        assert_eq!(SMALL.width("1:0"), 3 + 1 + 1 + 1 + 4);
+        assert_eq!(
+            SMALL.bitmap("1:0"),
+            Bitmap::from_ascii(
+                "
+                .#.....##.
+                ##....#..#
+                .#..#.#..#
+                .#....#..#
+                .#..#.#..#
+                .#....#..#
+                ###....##.
+                ..........
+                "
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_characters_are_skipped() {
+        assert_eq!(SMALL.width("1x1"), SMALL.width("11"));
+        assert_eq!(SMALL.width("x"), 0);
+    }
+
+    #[test]
+    fn tall_digits_span_both_module_rows() {
+        assert_eq!(TALL.height(), 16);
+        let eight = TALL.bitmap("8");
+        let lit_rows: Vec<u32> = (0..16)
+            .filter(|y| (0..eight.width()).any(|x| eight.get(x, *y)))
+            .collect();
+        assert_eq!(lit_rows, (1..15).collect::<Vec<_>>());
+        // Four digits and a colon fit across a 32-pixel screen.
+        assert!(TALL.width("00:00") <= 32);
+    }
+
+    #[test]
+    fn icons_and_badges_are_square() {
+        for icon in [Icon::Wifi, Icon::Alarm, Icon::Sync] {
+            assert_eq!(icon.glyph().width(), Icon::SIZE, "{icon:?}");
+        }
+        for badge in [
+            Badge::Wifi,
+            Badge::AccessPoint,
+            Badge::Sync,
+            Badge::Alarm,
+            Badge::Star,
+        ] {
+            assert_eq!(badge.glyph().width(), Badge::SIZE, "{badge:?}");
+        }
+        let mut bitmap = Bitmap::new(8, 8);
+        Icon::Wifi.draw(Point::zero(), &mut bitmap).unwrap();
+        assert_eq!(
+            bitmap,
+            Bitmap::from_ascii(
+                "
+                ........
+                .######.
+                #......#
+                ..####..
+                .#....#.
+                ...##...
+                ...##...
+                ........
+                "
+            )
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! How the 8x8 modules of a screen are arranged and mounted.
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Clockwise rotation of a module, as mounted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    /// The module's own coordinates for the screen's (x, y) within it.
+    fn apply(&self, x: u32, y: u32) -> (u32, u32) {
+        match self {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (y, 7 - x),
+            Rotation::Deg180 => (7 - x, 7 - y),
+            Rotation::Deg270 => (7 - y, x),
+        }
+    }
+}
+
+/// One 8x8 module, as mounted.
+///
+/// Its pixels are first rotated, then mirrored along the module's own axes by `invert_x` and
+/// `invert_y`, for modules wired back to front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub rotation: Rotation,
+    /// Position of the module's MAX7219 in the chain.
+    pub physical_posn: u8,
+}
+
+impl Segment {
+    /// Mounted upside down.
+    pub fn inverted(physical_posn: u8) -> Segment {
+        Segment::rotated(physical_posn, Rotation::Deg180)
+    }
+
+    pub fn normal(physical_posn: u8) -> Segment {
+        Segment::rotated(physical_posn, Rotation::Deg0)
+    }
+
+    pub fn rotated(physical_posn: u8, rotation: Rotation) -> Segment {
+        Segment {
+            invert_x: false,
+            invert_y: false,
+            rotation,
+            physical_posn,
+        }
+    }
+
+    /// The module's own coordinates for the screen's (x, y) within it.
+    fn map(&self, x: u32, y: u32) -> (u32, u32) {
+        let (x, y) = self.rotation.apply(x, y);
+        let x = if self.invert_x { 7 - x } else { x };
+        let y = if self.invert_y { 7 - y } else { y };
+        (x, y)
+    }
+}
+
+/// Why a grid of segments isn't a usable screen.  Rows and columns count from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    Empty,
+    Ragged {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    OutOfRange {
+        row: usize,
+        col: usize,
+        physical_posn: u8,
+        devices: usize,
+    },
+    Duplicate {
+        physical_posn: u8,
+        first: (usize, usize),
+        second: (usize, usize),
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Empty => write!(f, "Layout has no segments"),
+            LayoutError::Ragged { row, len, expected } => write!(
+                f,
+                "Row {row} has {len} segments but row 0 has {expected}: rows must be the same length"
+            ),
+            LayoutError::OutOfRange {
+                row,
+                col,
+                physical_posn,
+                devices,
+            } => write!(
+                f,
+                "Segment at row {row}, column {col} is at position {physical_posn} in the chain, \
+                 but a chain of {devices} only goes up to {}",
+                devices - 1
+            ),
+            LayoutError::Duplicate {
+                physical_posn,
+                first,
+                second,
+            } => write!(
+                f,
+                "Segments at row {}, column {} and row {}, column {} are both at position \
+                 {physical_posn} in the chain",
+                first.0, first.1, second.0, second.1
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LayoutError {}
+
+/// A grid of modules, one per MAX7219 in the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenConfig {
+    /// Modules in reading order: left to right, then top to bottom.
+    segments: Vec<Segment>,
+    /// Modules in each row of the grid.
+    row_length: usize,
+}
+
+impl ScreenConfig {
+    /// A screen from rows of segments, top to bottom.  Every row must be the same length, and the
+    /// segments' positions must cover the chain exactly once.
+    pub fn new(grid: Vec<Vec<Segment>>) -> Result<ScreenConfig, LayoutError> {
+        let row_length = grid.first().map_or(0, Vec::len);
+        if row_length == 0 {
+            return Err(LayoutError::Empty);
+        }
+        if let Some((row, segments)) = grid
+            .iter()
+            .enumerate()
+            .find(|(_, segments)| segments.len() != row_length)
+        {
+            return Err(LayoutError::Ragged {
+                row,
+                len: segments.len(),
+                expected: row_length,
+            });
+        }
+
+        let devices = grid.len() * row_length;
+        let mut seen: Vec<Option<(usize, usize)>> = alloc::vec![None; devices];
+        for (row, segments) in grid.iter().enumerate() {
+            for (col, segment) in segments.iter().enumerate() {
+                let physical_posn = segment.physical_posn;
+                let Some(slot) = seen.get_mut(physical_posn as usize) else {
+                    return Err(LayoutError::OutOfRange {
+                        row,
+                        col,
+                        physical_posn,
+                        devices,
+                    });
+                };
+                if let Some(first) = slot {
+                    return Err(LayoutError::Duplicate {
+                        physical_posn,
+                        first: *first,
+                        second: (row, col),
+                    });
+                }
+                *slot = Some((row, col));
+            }
+        }
+
+        Ok(ScreenConfig {
+            segments: grid.into_iter().flatten().collect(),
+            row_length,
+        })
+    }
+
+    /// Number of MAX7219s in the chain.
+    pub fn n_displays(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn cols(&self) -> u32 {
+        self.row_length as u32 * 8
+    }
+
+    pub fn rows(&self) -> u32 {
+        (self.segments.len() / self.row_length) as u32 * 8
+    }
+
+    /// Where the pixel at (x, y) lives: the framebuffer row (8 per display, in chain order) and
+    /// the bit within it.  `None` if it's off the screen.
+    pub fn position(&self, x: u32, y: u32) -> Option<(usize, u8)> {
+        if x >= self.cols() || y >= self.rows() {
+            return None;
+        }
+        let segment_no = x as usize / 8 + (y as usize / 8 * self.row_length);
+        let segment = &self.segments[segment_no];
+        let (x, y) = segment.map(x % 8, y % 8);
+
+        let row_index = segment.physical_posn as usize * 8 + y as usize;
+        Some((row_index, 0b1000_0000 >> x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::*;
+    use alloc::{string::ToString, vec};
+
+    fn square(segment: Segment) -> ScreenConfig {
+        ScreenConfig::new(vec![vec![segment]]).unwrap()
+    }
+
+    #[test]
+    fn dimensions_come_from_the_grid() {
+        let config = ScreenConfig::new(vec![
+            vec![Segment::normal(0), Segment::normal(1), Segment::normal(2)],
+            vec![Segment::normal(3), Segment::normal(4), Segment::normal(5)],
+        ])
+        .unwrap();
+        assert_eq!(config.n_displays(), 6);
+        assert_eq!((config.cols(), config.rows()), (24, 16));
+        assert_eq!(config.position(23, 15), Some((5 * 8 + 7, 0b0000_0001)));
+        assert_eq!(config.position(24, 0), None);
+        assert_eq!(config.position(0, 16), None);
+    }
+
+    #[test]
+    fn rotations_move_the_top_left_corner() {
+        let corner = |rotation| square(Segment::rotated(0, rotation)).position(0, 0);
+        assert_eq!(corner(Rotation::Deg0), Some((0, 0b1000_0000)));
+        assert_eq!(corner(Rotation::Deg90), Some((7, 0b1000_0000)));
+        assert_eq!(corner(Rotation::Deg180), Some((7, 0b0000_0001)));
+        assert_eq!(corner(Rotation::Deg270), Some((0, 0b0000_0001)));
+    }
+
+    #[test]
+    fn rotations_compose() {
+        // A quarter turn four times, or two half turns, is no turn at all.
+        let quarter = |(x, y)| Rotation::Deg90.apply(x, y);
+        let half = |(x, y)| Rotation::Deg180.apply(x, y);
+        for x in 0..8 {
+            for y in 0..8 {
+                assert_eq!(quarter(quarter(quarter(quarter((x, y))))), (x, y));
+                assert_eq!(quarter(quarter((x, y))), half((x, y)));
+                assert_eq!(
+                    quarter(quarter(quarter((x, y)))),
+                    Rotation::Deg270.apply(x, y)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inverting_both_axes_is_a_half_turn() {
+        let flipped = Segment {
+            invert_x: true,
+            invert_y: true,
+            ..Segment::normal(0)
+        };
+        let (flipped, inverted) = (square(flipped), square(Segment::inverted(0)));
+        for x in 0..8 {
+            for y in 0..8 {
+                assert_eq!(flipped.position(x, y), inverted.position(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_layouts_are_described() {
+        let error = |grid| ScreenConfig::new(grid).unwrap_err();
+        assert_eq!(error(vec![]), LayoutError::Empty);
+        assert_eq!(error(vec![vec![]]), LayoutError::Empty);
+        assert_eq!(
+            error(vec![
+                vec![Segment::normal(0), Segment::normal(1)],
+                vec![Segment::normal(2)],
+            ]),
+            LayoutError::Ragged {
+                row: 1,
+                len: 1,
+                expected: 2
+            }
+        );
+        assert_eq!(
+            error(vec![vec![Segment::normal(0), Segment::normal(2)]]),
+            LayoutError::OutOfRange {
+                row: 0,
+                col: 1,
+                physical_posn: 2,
+                devices: 2
+            }
+        );
+        let duplicate = error(vec![
+            vec![Segment::normal(0), Segment::normal(1)],
+            vec![Segment::normal(1), Segment::normal(3)],
+        ]);
+        assert_eq!(
+            duplicate,
+            LayoutError::Duplicate {
+                physical_posn: 1,
+                first: (0, 1),
+                second: (1, 0)
+            }
+        );
+        assert_eq!(
+            duplicate.to_string(),
+            "Segments at row 0, column 1 and row 1, column 0 are both at position 1 in the chain"
+        );
+    }
+}
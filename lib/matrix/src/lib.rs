@@ -0,0 +1,15 @@
+//! A [`DrawTarget`](embedded_graphics::draw_target::DrawTarget) for grids of 8x8 LED matrices
+//! driven by a chain of MAX7219s.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use max7219;
+
+pub mod animation;
+pub mod bitmap;
+pub mod font;
+pub mod layout;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod screen;
@@ -0,0 +1,171 @@
+//! A MAX7219 chain simulated behind fake pins.
+//!
+//! [`display`] wires a real [`MAX7219`] driver to a [`Chain`], which decodes the bitstream on its
+//! pins the way the chips would, so tests and simulators can see exactly what would be lit.
+use alloc::{vec, vec::Vec};
+use core::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use embedded_hal::digital::v2::OutputPin;
+use max7219::{connectors::PinConnector, MAX7219};
+
+/// Register addresses.  Digits 0-7 (the rows of a matrix) are at 1-8.
+pub mod register {
+    pub const NO_OP: usize = 0x00;
+    pub const DIGIT_0: usize = 0x01;
+    pub const DECODE_MODE: usize = 0x09;
+    pub const INTENSITY: usize = 0x0A;
+    pub const SCAN_LIMIT: usize = 0x0B;
+    pub const SHUTDOWN: usize = 0x0C;
+    pub const DISPLAY_TEST: usize = 0x0F;
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Line {
+    Data,
+    Select,
+    Clock,
+}
+
+/// A daisy chain of MAX7219s, as seen from the three pins which drive it.
+///
+/// Bits are shifted in on each rising clock edge while chip select is low, and latched when it
+/// goes high again: the stream is split into 16-bit words, the kth going to the kth device.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    data: bool,
+    clock: bool,
+    selected: bool,
+    bits: Vec<bool>,
+    registers: Vec<[u8; 16]>,
+}
+
+impl Chain {
+    /// A chain of `devices` chips, all registers zero.
+    pub fn new(devices: usize) -> Chain {
+        Chain {
+            data: false,
+            clock: false,
+            selected: false,
+            bits: Vec::new(),
+            registers: vec![[0; 16]; devices],
+        }
+    }
+
+    fn set(&mut self, line: Line, high: bool) {
+        match line {
+            Line::Data => self.data = high,
+            Line::Clock => {
+                if high && !self.clock && self.selected {
+                    self.bits.push(self.data);
+                }
+                self.clock = high;
+            }
+            Line::Select => {
+                if !high && !self.selected {
+                    self.bits.clear();
+                } else if high && self.selected {
+                    self.latch();
+                }
+                self.selected = !high;
+            }
+        }
+    }
+
+    fn latch(&mut self) {
+        let byte = |bits: &[bool]| bits.iter().fold(0, |byte, bit| byte << 1 | *bit as u8);
+        for (registers, word) in self.registers.iter_mut().zip(self.bits.chunks_exact(16)) {
+            let address = (byte(&word[..8]) & 0x0F) as usize;
+            if address != register::NO_OP {
+                registers[address] = byte(&word[8..]);
+            }
+        }
+    }
+
+    pub fn devices(&self) -> usize {
+        self.registers.len()
+    }
+
+    /// Every register of `device`, by address.
+    pub fn registers(&self, device: usize) -> &[u8; 16] {
+        &self.registers[device]
+    }
+
+    /// Digit register `row` (0-7) of `device`.
+    pub fn row(&self, device: usize, row: usize) -> u8 {
+        self.registers[device][register::DIGIT_0 + row]
+    }
+
+    /// Whether the LEDs under `mask` in `row` of `device` are lit: set while powered on, or
+    /// anything during a display test.
+    pub fn lit(&self, device: usize, row: usize, mask: u8) -> bool {
+        let registers = &self.registers[device];
+        registers[register::DISPLAY_TEST] & 1 == 1
+            || (registers[register::SHUTDOWN] & 1 == 1 && self.row(device, row) & mask == mask)
+    }
+}
+
+/// One of the pins driving a [`Chain`].
+#[derive(Debug)]
+pub struct MockPin {
+    chain: Arc<Mutex<Chain>>,
+    line: Line,
+}
+
+impl OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.chain.lock().unwrap().set(self.line, false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.chain.lock().unwrap().set(self.line, true);
+        Ok(())
+    }
+}
+
+pub type MockConnector = PinConnector<MockPin, MockPin, MockPin>;
+pub type MockDisplay = MAX7219<MockConnector>;
+
+/// A driver for a new chain of `devices`, and the chain to inspect.
+pub fn display(devices: usize) -> (MockDisplay, Arc<Mutex<Chain>>) {
+    let chain = Arc::new(Mutex::new(Chain::new(devices)));
+    let [data, cs, clk] = [Line::Data, Line::Select, Line::Clock].map(|line| MockPin {
+        chain: chain.clone(),
+        line,
+    });
+    let display =
+        MAX7219::from_pins(devices, data, cs, clk).expect("Mock pins can't fail to initialise");
+    (display, chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{display, register};
+
+    #[test]
+    fn driver_commands_reach_the_addressed_chip() {
+        let (mut display, chain) = display(3);
+        display.power_on().unwrap();
+        display.set_intensity(1, 7).unwrap();
+        display.write_raw_byte(2, 8, 0b1010_0101).unwrap();
+        let chain = chain.lock().unwrap();
+        assert_eq!(chain.registers(0)[register::INTENSITY], 0);
+        assert_eq!(chain.registers(1)[register::INTENSITY], 7);
+        assert_eq!(chain.row(2, 7), 0b1010_0101);
+        assert_eq!(chain.row(1, 7), 0);
+        assert!(chain.lit(2, 7, 0b1000_0000));
+        assert!(!chain.lit(2, 7, 0b0100_0000));
+    }
+
+    #[test]
+    fn nothing_is_lit_until_powered_on() {
+        let (mut display, chain) = display(1);
+        display.write_raw_byte(0, 1, 0xFF).unwrap();
+        assert!(!chain.lock().unwrap().lit(0, 0, 0xFF));
+        display.power_on().unwrap();
+        assert!(chain.lock().unwrap().lit(0, 0, 0xFF));
+    }
+}
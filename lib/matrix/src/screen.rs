@@ -0,0 +1,323 @@
+use alloc::vec::Vec;
+use core::{fmt, iter};
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+
+use max7219::{connectors::Connector, DataError, DecodeMode, MAX7219};
+
+use crate::{
+    bitmap::Bitmap,
+    font::{Font, Icon},
+    layout::ScreenConfig,
+};
+
+#[derive(Debug)]
+pub struct ScreenBuilder {
+    config: ScreenConfig,
+    framebuffer: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScreenError;
+
+impl fmt::Display for ScreenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Display error")
+    }
+}
+
+impl From<DataError> for ScreenError {
+    /// Cast a DataError enum into a screen error.
+    /// The only information in a DataError is the mode (spi/pin), which isn't worth preserving.
+    fn from(_value: DataError) -> Self {
+        ScreenError
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScreenError {}
+
+pub struct Screen<T>
+where
+    T: Connector,
+{
+    config: ScreenConfig,
+    framebuffer: Vec<u8>,
+    last_framebuffer: Vec<u8>,
+    display: MAX7219<T>,
+}
+
+impl ScreenBuilder {
+    pub fn new(config: ScreenConfig) -> ScreenBuilder {
+        let len = config.n_displays();
+        ScreenBuilder {
+            config,
+            framebuffer: iter::repeat(0).take(len * 8).collect(),
+        }
+    }
+
+    pub fn build<T>(self, display: MAX7219<T>) -> Result<Screen<T>, ScreenError>
+    where
+        T: Connector,
+    {
+        let last_framebuffer = self.framebuffer.clone();
+        let mut screen = Screen {
+            config: self.config,
+            framebuffer: self.framebuffer,
+            last_framebuffer,
+            display,
+        };
+        screen.display.power_on()?;
+        for n in 0..screen.config.n_displays() {
+            screen.display.set_decode_mode(n, DecodeMode::NoDecode)?;
+            screen.display.clear_display(n)?;
+            screen.display.set_intensity(n, 0)?;
+        }
+        Ok(screen)
+    }
+}
+
+impl<T> Screen<T>
+where
+    T: Connector,
+{
+    pub fn set_brightness(&mut self, brightness: u8) -> Result<(), ScreenError> {
+        for n in 0..self.config.n_displays() {
+            self.display.set_intensity(n, brightness)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), ScreenError> {
+        let updates = iter::zip(self.framebuffer.chunks(8), self.last_framebuffer.chunks(8))
+            .enumerate()
+            .flat_map(|(display, (new, old))| {
+                iter::zip(new, old)
+                    .enumerate()
+                    .filter_map(move |(row, (new, old))| match new == old {
+                        true => None,
+                        false => Some((display, row + 1, new)),
+                    })
+            });
+
+        for (display, row, new) in updates {
+            self.display.write_raw_byte(display, row as u8, *new)?;
+        }
+
+        self.last_framebuffer = self.framebuffer.clone();
+        Ok(())
+    }
+
+    /// Set the pixel at (x, y), if it's on the screen.
+    pub fn blit(&mut self, x: u32, y: u32, on: bool) {
+        let Some((row_index, mask)) = self.config.position(x, y) else {
+            return;
+        };
+        let mut row = self.framebuffer[row_index];
+        if on {
+            row |= mask;
+        } else {
+            row &= !mask;
+        }
+        self.framebuffer[row_index] = row;
+    }
+
+    /// Copy `bitmap` onto the screen, top left to top left.
+    pub fn show(&mut self, bitmap: &Bitmap) {
+        for (x, y) in bitmap.coordinates() {
+            self.blit(x, y, bitmap.get(x, y));
+        }
+    }
+
+    /// Write `text` in `font` with its top left at `origin`, returning where the next character
+    /// would go.
+    pub fn write(&mut self, font: &Font, text: &str, origin: Point) -> Point {
+        match font.draw(text, origin, self) {
+            Ok(next) => next,
+            Err(infallible) => match infallible {},
+        }
+    }
+
+    /// Draw `icon` with its top left at `origin`.
+    pub fn icon(&mut self, icon: Icon, origin: Point) {
+        match icon.draw(origin, self) {
+            Ok(()) => (),
+            Err(infallible) => match infallible {},
+        }
+    }
+
+    /// What's been drawn, whether or not it's been flushed yet.
+    pub fn bitmap(&self) -> Bitmap {
+        Bitmap::from_framebuffer(&self.config, &self.framebuffer)
+    }
+
+    pub fn clear(&mut self) {
+        self.framebuffer = iter::repeat(0).take(self.framebuffer.len()).collect();
+    }
+}
+
+impl<T> DrawTarget for Screen<T>
+where
+    T: Connector,
+{
+    type Error = core::convert::Infallible;
+    type Color = BinaryColor;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        for Pixel(coord, color) in pixels.into_iter() {
+            if let Ok((x, y)) = coord.try_into() {
+                self.blit(x, y, color.is_on());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> OriginDimensions for Screen<T>
+where
+    T: Connector,
+{
+    fn size(&self) -> Size {
+        Size::new(self.config.cols(), self.config.rows())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitmap::Bitmap;
+    use crate::font::{Icon, TALL};
+    use crate::layout::{ScreenConfig, Segment};
+    use crate::mock::{self, register, Chain, MockConnector};
+    use crate::screen::*;
+    use alloc::vec;
+    use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+    use std::sync::{Arc, Mutex};
+
+    /// Two rows of two modules: the top row mounted normally at the far end of the chain, the
+    /// bottom row upside down.
+    fn config() -> ScreenConfig {
+        ScreenConfig::new(vec![
+            vec![Segment::normal(2), Segment::normal(3)],
+            vec![Segment::inverted(1), Segment::inverted(0)],
+        ])
+        .unwrap()
+    }
+
+    fn screen() -> (Screen<MockConnector>, Arc<Mutex<Chain>>) {
+        let (display, chain) = mock::display(4);
+        (ScreenBuilder::new(config()).build(display).unwrap(), chain)
+    }
+
+    /// Every lit LED, as (device, row, column).
+    fn lit(chain: &Mutex<Chain>) -> Vec<(usize, usize, u32)> {
+        let chain = chain.lock().unwrap();
+        let mut lit = Vec::new();
+        for device in 0..chain.devices() {
+            for row in 0..8 {
+                for col in 0..8 {
+                    if chain.lit(device, row, 0b1000_0000 >> col) {
+                        lit.push((device, row, col));
+                    }
+                }
+            }
+        }
+        lit
+    }
+
+    #[test]
+    fn build_initialises_every_device() {
+        let (_screen, chain) = screen();
+        let chain = chain.lock().unwrap();
+        for device in 0..4 {
+            let registers = chain.registers(device);
+            assert_eq!(registers[register::SHUTDOWN], 1);
+            assert_eq!(registers[register::DECODE_MODE], 0);
+            assert_eq!(registers[register::INTENSITY], 0);
+        }
+    }
+
+    #[test]
+    fn segments_map_to_their_devices() {
+        let corners = [
+            ((0, 0), (2, 0, 0)),
+            ((15, 0), (3, 0, 7)),
+            ((0, 15), (1, 0, 7)),
+            ((8, 8), (0, 7, 7)),
+            ((3, 9), (1, 6, 4)),
+        ];
+        for ((x, y), expected) in corners {
+            let (mut screen, chain) = screen();
+            screen.blit(x, y, true);
+            assert_eq!(lit(&chain), vec![], "nothing should be lit before flushing");
+            screen.flush().unwrap();
+            assert_eq!(lit(&chain), vec![expected], "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn blitting_off_the_screen_is_ignored() {
+        let (mut screen, chain) = screen();
+        screen.blit(16, 0, true);
+        screen.blit(0, 16, true);
+        screen.flush().unwrap();
+        assert_eq!(lit(&chain), vec![]);
+    }
+
+    #[test]
+    fn clearing_turns_pixels_off() {
+        let (mut screen, chain) = screen();
+        screen.blit(5, 5, true);
+        screen.blit(6, 5, true);
+        screen.flush().unwrap();
+        screen.blit(5, 5, false);
+        screen.flush().unwrap();
+        assert_eq!(lit(&chain), vec![(2, 5, 6)]);
+        screen.clear();
+        screen.flush().unwrap();
+        assert_eq!(lit(&chain), vec![]);
+    }
+
+    #[test]
+    fn drawing_clips_to_the_screen() {
+        let (mut screen, chain) = screen();
+        Rectangle::new(Point::new(-4, 14), Size::new(8, 8))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut screen)
+            .unwrap();
+        screen.flush().unwrap();
+        // (0..4, 14..16), on the upside-down bottom-left module.
+        let expected: Vec<_> = (0..2)
+            .flat_map(|row| (4..8).map(move |col| (1, row, col)))
+            .collect();
+        assert_eq!(lit(&chain), expected);
+    }
+
+    #[test]
+    fn text_and_icons_land_where_drawn() {
+        let (mut screen, _) = screen();
+        assert_eq!(screen.write(&TALL, "1", Point::zero()), Point::new(5, 0));
+        screen.icon(Icon::Alarm, Point::new(8, 8));
+        let bitmap = screen.bitmap();
+        assert_eq!(
+            bitmap.crop(Point::zero(), Size::new(4, 16)),
+            TALL.bitmap("1")
+        );
+        let mut alarm = Bitmap::new(8, 8);
+        Icon::Alarm.draw(Point::zero(), &mut alarm).unwrap();
+        assert_eq!(bitmap.crop(Point::new(8, 8), Size::new(8, 8)), alarm);
+    }
+
+    #[test]
+    fn brightness_reaches_every_device() {
+        let (mut screen, chain) = screen();
+        screen.set_brightness(9).unwrap();
+        let chain = chain.lock().unwrap();
+        for device in 0..4 {
+            assert_eq!(chain.registers(device)[register::INTENSITY], 9);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! The clock's animations, played over the face by the screen task.
+use std::time::{Duration, Instant};
+
+use embedded_graphics::prelude::*;
+use matrix::{
+    animation::{
+        Animation, Chained, Direction, Effect, Keyframe, Sequence, SpriteSheet, Transition,
+    },
+    bitmap::Bitmap,
+};
+use serde::{Deserialize, Serialize};
+
+/// Time between frames while animating, however often the clock itself is redrawn.
+pub const FRAME_TIME: Duration = Duration::from_millis(30);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationConfig {
+    /// Play a burst when a significant moment flashes.
+    pub significance: bool,
+    /// How the face changes when the config does.
+    pub transition: Effect,
+    pub transition_ms: u32,
+}
+
+impl Default for AnimationConfig {
+    fn default() -> AnimationConfig {
+        AnimationConfig {
+            significance: true,
+            transition: Effect::Slide(Direction::Up),
+            transition_ms: 300,
+        }
+    }
+}
+
+impl AnimationConfig {
+    pub fn transition(&self, from: Bitmap, to: Bitmap) -> Transition {
+        Transition {
+            effect: self.transition,
+            from,
+            to,
+            length: Duration::from_millis(self.transition_ms.into()),
+        }
+    }
+}
+
+/// A burst in four 8x8 frames: a dot, growing, bursting, then sparks.
+const BURST: &str = "
+    ...................##...#..##..#
+    .................#.##.#.........
+    ...........##.....#..#..........
+    ...##.....####..###..####......#
+    ...##.....####..###..####......#
+    ...........##.....#..#..........
+    .................#.##.#.........
+    ...................##...#..##..#
+";
+
+/// The screen clears, a burst goes off twice in the middle, and the face dissolves back in.
+pub fn significance(face: Bitmap) -> Chained {
+    let size = face.size();
+    let blank = Bitmap::new(size.width, size.height);
+    let sprites = SpriteSheet::new(
+        Bitmap::from_ascii(BURST).expect("Burst sprites are valid"),
+        Size::new(8, 8),
+    );
+    let centre = Point::new((size.width as i32 - 8) / 2, (size.height as i32 - 8) / 2);
+    let keyframes = (0..=4)
+        .map(|step| Keyframe {
+            at: Duration::from_millis(80 * step as u64),
+            sprite: step.min(3),
+            position: centre,
+        })
+        .collect();
+    let dissolve = |from: &Bitmap, to: &Bitmap, ms| Transition {
+        effect: Effect::Dissolve,
+        from: from.clone(),
+        to: to.clone(),
+        length: Duration::from_millis(ms),
+    };
+    Chained(vec![
+        Box::new(dissolve(&face, &blank, 150)),
+        Box::new(Sequence::new(blank.clone(), sprites, keyframes).repeat(2)),
+        Box::new(dissolve(&blank, &face, 400)),
+    ])
+}
+
+/// An animation in progress.
+pub struct Playing {
+    animation: Box<dyn Animation + Send>,
+    started: Instant,
+}
+
+impl Playing {
+    pub fn new(animation: impl Animation + Send + 'static) -> Playing {
+        Playing {
+            animation: Box::new(animation),
+            started: Instant::now(),
+        }
+    }
+
+    /// The frame to show now, or `None` once it's over.
+    pub fn frame(&self) -> Option<Bitmap> {
+        let elapsed = self.started.elapsed();
+        (elapsed < self.animation.length()).then(|| self.animation.frame(elapsed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use matrix::{animation::Animation, bitmap::Bitmap};
+
+    use crate::animations::significance;
+
+    #[test]
+    fn significance_starts_and_ends_on_the_face() {
+        let mut face = Bitmap::new(32, 16);
+        face.set(3, 4, true);
+        face.set(20, 11, true);
+        let animation = significance(face.clone());
+        assert_eq!(animation.length(), Duration::from_millis(150 + 640 + 400));
+        assert_eq!(animation.frame(Duration::ZERO), face);
+        assert_eq!(animation.frame(animation.length()), face);
+        // The burst is in the middle of an otherwise blank screen.
+        let burst = animation.frame(Duration::from_millis(150 + 200));
+        assert!(burst.get(12, 7) && !burst.get(3, 4) && !burst.get(20, 11));
+    }
+}
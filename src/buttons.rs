@@ -1,5 +1,5 @@
+use crate::hal::Delay;
 use embedded_hal::digital::InputPin;
-use esp_idf_hal::delay::Delay;
 
 use rgb::RGB8;
 
@@ -7,8 +7,13 @@ use crossbeam_channel::Sender;
 
 use crate::{config::Config, event::Event, leds::Pixel};
 
+#[allow(dead_code)] // TODO configurable button actions.
 type ActionFn = fn(&Actions) -> ();
 
+/// Presses held at least this long are long presses.
+const LONG_PRESS_MS: u32 = 600;
+
+#[allow(dead_code)] // TODO configurable button actions.
 pub struct Actions {
     pub short_left: ActionFn,
     pub long_left: ActionFn,
@@ -55,17 +60,20 @@ impl<T: InputPin> Buttons<T> {
         loop {
             if let Ok(true) = self.right_button.is_high() {
                 log::info!("Right button pressed");
+                let mut held_ms = 0;
                 while let Ok(true) = self.right_button.is_high() {
                     delay.delay_ms(1);
+                    held_ms += 1;
+                }
+                log::info!("Right button released after {held_ms}ms");
+                if held_ms >= LONG_PRESS_MS {
+                    self.config.face = self.config.face.next();
+                    log::info!("Showing the {:?} face", self.config.face);
+                } else {
+                    self.config.lamp_brightness = self.config.lamp_brightness.safe_add(step);
                 }
-                log::info!("Right button released");
-                self.config.lamp_brightness = self.config.lamp_brightness.safe_add(step);
                 log::info!("Config updated");
-                // FIXME why do we need to send all events twice?  Maybe log response here.
-                let r = tx.send(Event::ChangeConfig(self.config.clone()));
-                log::info!("Sent! {r:?}");
-                let r = tx.send(Event::ChangeConfig(self.config.clone()));
-                log::info!("Sent! {r:?}");
+                let _ = tx.send(Event::ChangeConfig(self.config.clone()));
             }
 
             if let Ok(true) = self.left_button.is_high() {
@@ -74,7 +82,6 @@ impl<T: InputPin> Buttons<T> {
                 }
                 self.config.lamp_brightness = self.config.lamp_brightness.safe_sub(step);
                 let _ = tx.send(Event::ChangeConfig(self.config.clone()));
-                let _ = tx.send(Event::ChangeConfig(self.config.clone()));
             }
             delay.delay_ms(1);
         }
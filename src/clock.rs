@@ -1,109 +1,579 @@
-use anyhow::{Context, Result};
-use chrono::Local;
-use crossbeam_channel::{Receiver, Sender};
-use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
-use esp_idf_hal::{
-    delay::Delay,
-    sys::{setenv, tzset},
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use bus::BusReader;
+use chrono::NaiveDateTime;
+use crossbeam_channel::Sender;
+use embedded_graphics::prelude::*;
+use logic::{
+    date,
+    format::Resolution,
+    pattern::Pattern,
+    registry::{self, RuleSet, UnknownRule},
+    rules::FlashStyle,
+    significance::{explain_above, explain_minute},
+    tracker::Tracker,
 };
-use logic::significance::is_significant;
 
-use std::ffi::CString;
+use matrix::{bitmap::Bitmap, max7219::connectors::Connector, screen::Screen};
 
-use max7219::connectors::Connector;
-use u8g2_fonts::{
-    self, fonts,
-    types::{FontColor, HorizontalAlignment, VerticalPosition},
-    FontRenderer,
+use crate::hal::{self, Delay};
+use crate::{
+    animations::{self, Playing, FRAME_TIME},
+    config::{default_rules, Config, HistoryHandler},
+    dimmer::{Clock, Dimmer},
+    event::Event,
+    faces::Caption,
+    message::Message,
+    status::Status,
 };
 
-use crate::screen::Screen;
-use crate::{config::Config, event::Event};
+/// The user's patterns from `config`, skipping any that don't parse.
+fn custom_rules(config: &Config) -> RuleSet {
+    let mut rules = RuleSet::empty();
+    for source in &config.patterns {
+        match Pattern::parse(source) {
+            Ok(pattern) => rules.push(Box::new(pattern)),
+            Err(e) => log::warn!("Ignoring pattern '{source}': {e}"),
+        }
+    }
+    rules
+}
 
-fn flash(tx: &Sender<Event>) {
-    let _ = tx.try_send(Event::Flash);
+/// The built-in rules named in `config`, skipping any that don't exist, or `None` for the default
+/// set, which has faster paths of its own.
+fn builtin_rules(config: &Config) -> Option<RuleSet> {
+    if config.rules == default_rules() {
+        return None;
+    }
+    let known: Vec<&String> = config
+        .rules
+        .iter()
+        .filter(|name| {
+            let known = registry::builtin(name).is_some();
+            if !known {
+                log::warn!("Ignoring rule: {}", UnknownRule(name.to_string()));
+            }
+            known
+        })
+        .collect();
+    Some(RuleSet::from_names(&known).expect("Unknown rules were skipped"))
 }
 
-fn show_time<T>(screen: &mut Screen<T>, significant_mode: bool, tx: &Sender<Event>) -> Result<()>
+/// Significance state carried from one redraw to the next.
+struct Detector {
+    /// The configured built-in rules, unless they're the default.
+    builtin: Option<RuleSet>,
+    custom: RuleSet,
+    tracker: Tracker,
+    history: HistoryHandler,
+}
+
+impl Detector {
+    fn new(config: &Config, history: HistoryHandler) -> Detector {
+        Detector {
+            builtin: builtin_rules(config),
+            custom: custom_rules(config),
+            tracker: Tracker::new(config.significance_cooldown.clone()),
+            history,
+        }
+    }
+
+    fn reconfigure(&mut self, config: &Config) {
+        self.builtin = builtin_rules(config);
+        self.custom = custom_rules(config);
+        self.tracker
+            .set_cooldown(config.significance_cooldown.clone());
+    }
+
+    /// What's significant about `dt`, flashing and recording each new significant moment.
+    fn detect(
+        &mut self,
+        config: &Config,
+        flashes: &Sender<FlashStyle>,
+        dt: NaiveDateTime,
+    ) -> Detection {
+        if !config.significant_mode {
+            return Detection::default();
+        }
+        let displayed = config.hour_format.displayed(&dt);
+        let date_significance = date::explain(&dt, config.date_format, config.hour_format);
+        let threshold = config.significance_threshold;
+        let digits = config.resolution.digits(&displayed);
+        let significance = date_significance
+            .or_else(|| match (&self.builtin, config.resolution) {
+                (Some(rules), _) => rules.explain_above(&digits, threshold),
+                (None, Resolution::Seconds) => explain_above(displayed, threshold),
+                (None, Resolution::Minutes) => explain_minute(displayed, threshold),
+            })
+            .or_else(|| self.custom.explain_digits(&digits))
+            .or_else(|| config.maths.explain_digits(&digits));
+        let found = match (&date_significance, &significance) {
+            (Some(_), _) => Some(Found::Date),
+            (None, Some(significance)) => Some(Found::Time(significance.to_string())),
+            (None, None) => None,
+        };
+        // We redraw several times a second, but only flash once per significant moment.  Without
+        // seconds that's once a minute, at the start, as it is for date patterns, which mostly
+        // hold for the whole minute.
+        let moment = match found {
+            Some(Found::Date) => Resolution::Minutes.moment(&dt),
+            _ => config.resolution.moment(&dt),
+        };
+        let fired = self.tracker.observe(moment, significance);
+        if let Some(significance) = &fired {
+            let _ = flashes.try_send(significance.flash);
+            // Moments can come every few seconds, which is far too often to write to flash.
+            self.history.update_deferred(Instant::now(), |history| {
+                history.record(moment, significance.rule)
+            });
+        }
+        Detection {
+            found,
+            fired: fired.is_some(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Detection {
+    found: Option<Found>,
+    /// Whether this is a new significant moment, and so was flashed.
+    fired: bool,
+}
+
+/// What a redraw found significant.
+enum Found {
+    /// A date pattern, captioned with the date.
+    Date,
+    /// A time pattern, captioned with its description.
+    Time(String),
+}
+
+/// Draw the configured face for `dt`, local time, ready to flush.  Returns whether it's a new
+/// significant moment, which has been flashed.
+fn show_time<T>(
+    screen: &mut Screen<T>,
+    config: &Config,
+    detector: &mut Detector,
+    flashes: &Sender<FlashStyle>,
+    dt: NaiveDateTime,
+) -> Result<bool>
 where
     T: Connector,
 {
     screen.clear();
 
-    let dt = Local::now();
-    if significant_mode && is_significant(dt) {
-        flash(tx);
-    }
-
-    let hm = dt.format("%H:%M");
-    let s = dt.format("%S");
-
-    let large_font = FontRenderer::new::<fonts::u8g2_font_5x7_tf>();
-    // let small_font = FontRenderer::new::<fonts::u8g2_font_squeezed_r7_tr>();
-    let tiny_font = FontRenderer::new::<fonts::u8g2_font_u8glib_4_tf>();
-    large_font
-        .render_aligned(
-            format_args!("{}", hm),
-            screen.bounding_box().center(),
-            VerticalPosition::Center,
-            HorizontalAlignment::Center,
-            FontColor::Transparent(BinaryColor::On),
-            screen,
-        )
-        .unwrap(); // infallible
-    let bottom_rhc = screen
-        .bounding_box()
-        .bottom_right()
-        .context("Screen has no bottom rhc")?;
-
-    tiny_font
-        .render_aligned(
-            format_args!("{}", s),
-            bottom_rhc + Point::new(1, 2),
-            VerticalPosition::Bottom,
-            HorizontalAlignment::Right,
-            FontColor::Transparent(BinaryColor::On),
-            screen,
-        )
-        .unwrap(); // infallible
-
-    screen.flush()?;
-
-    Ok(())
+    let Detection { found, fired } = detector.detect(config, flashes, dt);
+
+    // Date patterns show the date they matched; the rest say what they were.  Otherwise show how
+    // many significant moments there have been today.
+    let caption = match found {
+        Some(Found::Date) => Caption::Label(dt.format(config.date_format.day_month()).to_string()),
+        Some(Found::Time(label)) => Caption::Label(label),
+        None if config.significant_mode => {
+            Caption::Count(detector.history.current().count_on(dt.date()))
+        }
+        None => Caption::None,
+    };
+    config.face.draw(config, &dt, caption, screen)?;
+
+    Ok(fired)
+}
+
+/// Whether the face looks different under `new`, and so should transition rather than cut.
+fn face_changed(old: &Config, new: &Config) -> bool {
+    old.hour_format != new.hour_format
+        || old.resolution != new.resolution
+        || old.significant_mode != new.significant_mode
+        || old.face != new.face
 }
 
-pub unsafe fn set_timezone() {
-    let tz = CString::new("TZ").expect("Unable to generate 'TZ' as string");
-    // let zone = CString::new("CET-1CEST,M3.5.0,M10.5.0/3").expect("Unable to generate timezone string");
-    let zone =
-        CString::new("GMT0BST,M3.5.0/1,M10.5.0").expect("Unable to generate timezone string");
-    setenv(tz.as_ptr(), zone.as_ptr(), 1);
-    tzset();
+pub fn set_timezone() {
+    // hal::set_timezone("CET-1CEST,M3.5.0,M10.5.0/3");
+    hal::set_timezone("GMT0BST,M3.5.0/1,M10.5.0");
 }
 
-pub fn screen_loop<T>(
+/// Draw the clock, as of `clock`.  Significant moments are flashed by sending their style to the
+/// lamp on `flashes`, which has that channel to itself.
+pub fn screen_loop<T, C>(
     mut screen: Screen<T>,
-    rx: Receiver<Event>,
-    tx: Sender<Event>,
+    mut rx: BusReader<Event>,
+    flashes: Sender<FlashStyle>,
     config: Config,
+    history: HistoryHandler,
+    clock: C,
 ) -> !
 where
     T: Connector,
+    C: Clock,
 {
-    unsafe { set_timezone() };
+    set_timezone();
     let delay = Delay::new_default();
     let mut config = config;
+    let mut detector = Detector::new(&config, history);
+    let mut message: Option<Message> = None;
+    let mut status = Status::new(config.significant_mode, Instant::now());
+    let mut playing: Option<Playing> = None;
+    // The face from before a config change, to transition from once the new one is drawn.
+    let mut previous: Option<Bitmap> = None;
+    let mut dimmer = Dimmer::new(&clock, config.schedule.clone());
+    // Blank until the schedule next changes, or a `Show` event.
+    let mut hidden = false;
     loop {
-        if let Err(e) = show_time(&mut screen, config.significant_mode, &tx) {
-            log::error!("Show time failed: {e:?}")
+        if let Some(setting) = dimmer.poll() {
+            if let Err(e) = screen.set_brightness(setting.level.screen) {
+                log::error!("Set brightness failed: {e:?}")
+            };
+            hidden = setting.blank;
+        }
+        let now = clock.now().naive_local();
+        if hidden {
+            // Nothing's drawn, but significance is still flashed on the lamp.
+            detector.detect(&config, &flashes, now);
+            screen.clear();
+            playing = None;
+            previous = None;
+        }
+        // Messages cover the clock until they're done, but significance is still flashed.
+        let showing = hidden
+            || match &message {
+                Some(shown) => {
+                    detector.detect(&config, &flashes, now);
+                    shown.show(&mut screen).unwrap_or_else(|e| {
+                        log::error!("Show message failed: {e:?}");
+                        false
+                    })
+                }
+                None => false,
+            };
+        if !showing {
+            message = None;
+            match show_time(&mut screen, &config, &mut detector, &flashes, now) {
+                Ok(true) if config.animation.significance => {
+                    playing = Some(Playing::new(animations::significance(screen.bitmap())));
+                }
+                Ok(_) => (),
+                Err(e) => log::error!("Show time failed: {e:?}"),
+            };
+            if let Err(e) = status.draw(&config.status, Instant::now(), &mut screen) {
+                log::error!("Show status failed: {e:?}")
+            };
+            if let Some(from) = previous.take() {
+                let transition = config.animation.transition(from, screen.bitmap());
+                playing = Some(Playing::new(transition));
+            }
+        }
+        // Animations play over whatever was drawn, until they're done.
+        match playing.as_ref().and_then(Playing::frame) {
+            Some(frame) => screen.show(&frame),
+            None => playing = None,
+        }
+        if let Err(e) = screen.flush() {
+            log::error!("Flush failed: {e:?}")
         };
-        match rx.try_recv() {
-            Ok(Event::ChangeBrightness(val)) => {
-                let _ = screen.set_brightness(val);
+        detector.history.persist_due(Instant::now());
+        // Take everything waiting, so a burst of events can't fill the bus and hold up the tasks
+        // sending them.
+        while let Ok(event) = rx.try_recv() {
+            status.update(&event, Instant::now());
+            match event {
+                Event::ChangeBrightness(val) => {
+                    let _ = screen.set_brightness(val);
+                }
+                Event::Hide => hidden = true,
+                Event::Show => hidden = false,
+                Event::ChangeConfig(new_config) => {
+                    if face_changed(&config, &new_config) && !hidden {
+                        previous = Some(screen.bitmap());
+                    }
+                    detector.reconfigure(&new_config);
+                    dimmer.reconfigure(&new_config.schedule);
+                    config = new_config;
+                }
+                Event::ShowStatic(text) => {
+                    let width = screen.bounding_box().size.width;
+                    message = Message::new(text, width, &config.message)
+                        .map_err(|e| log::error!("Can't show message: {e:?}"))
+                        .ok();
+                }
+                _ => (),
+            };
+        }
+        // Redraws happen at least this often, and animations take their time from the clock, so
+        // they play at the same speed however often the face is redrawn.
+        let frame_time = match (&playing, &message) {
+            (Some(_), _) => FRAME_TIME,
+            (None, Some(message)) => message.frame_time(),
+            (None, None) => Duration::from_millis(100),
+        };
+        delay.delay_ms(frame_time.as_millis() as u32);
+    }
+}
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use std::{
+        env, fs,
+        path::Path,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use bus::Bus;
+    use chrono::{FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+    use crossbeam_channel::bounded;
+    use embedded_graphics::prelude::*;
+    use logic::{
+        format::{HourFormat, Resolution},
+        rules::FlashStyle,
+    };
+    use matrix::{
+        bitmap::Bitmap,
+        font::Badge,
+        mock::{self, Chain},
+        screen::ScreenBuilder,
+    };
+
+    use crate::clock::{screen_loop, show_time, Detector};
+    use crate::config::{Config, HistoryHandler};
+    use crate::dimmer::FakeClock;
+    use crate::event::Event;
+    use crate::faces::Face;
+    use crate::status::{Status, StatusConfig};
+
+    fn at(h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 11, 14)
+            .unwrap()
+            .and_hms_opt(h, m, s)
+            .unwrap()
+    }
+
+    /// What the face shows at `dt`, and the flashes it sent.
+    fn render(name: &str, config: &Config, dt: NaiveDateTime) -> (Bitmap, Vec<FlashStyle>) {
+        let (display, _) = mock::display(8);
+        let mut screen = ScreenBuilder::new(crate::screen_config())
+            .build(display)
+            .unwrap();
+        let history = env::temp_dir().join(format!("significant-clock-{name}.json"));
+        let _ = fs::remove_file(&history);
+        let mut detector = Detector::new(config, HistoryHandler::new(&history));
+        let (tx, rx) = bounded(8);
+        show_time(&mut screen, config, &mut detector, &tx, dt).unwrap();
+        (screen.bitmap(), rx.try_iter().collect())
+    }
+
+    /// Compare with `snapshots/<name>.txt`.  Missing snapshots are recorded (except on CI, where
+    /// they must already be committed), and `UPDATE_SNAPSHOTS=1` re-records them all.
+    fn assert_snapshot(name: &str, bitmap: &Bitmap) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("snapshots")
+            .join(format!("{name}.txt"));
+        let actual = bitmap.to_ascii();
+        let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+        match fs::read_to_string(&path) {
+            Ok(expected) if !update => assert_eq!(
+                actual,
+                expected,
+                "'{name}' differs from {}.  If that's intended, re-record it with \
+                 `UPDATE_SNAPSHOTS=1 make test-sim`.",
+                path.display()
+            ),
+            Err(_) if !update && env::var_os("CI").is_some() => panic!(
+                "No snapshot at {}: record it with `make test-sim` and commit it",
+                path.display()
+            ),
+            _ => {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, actual).unwrap();
+                eprintln!("Recorded {}", path.display());
             }
-            Ok(Event::ChangeConfig(new_config)) => config = new_config,
-            _ => (),
+        }
+    }
+
+    fn plain() -> Config {
+        Config {
+            significant_mode: false,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn twenty_four_hour() {
+        let (bitmap, flashes) = render("24h", &plain(), at(9, 41, 7));
+        assert_snapshot("24h", &bitmap);
+        assert!(flashes.is_empty());
+    }
+
+    #[test]
+    fn twelve_hour() {
+        let config = Config {
+            hour_format: HourFormat::Twelve,
+            ..plain()
+        };
+        let (bitmap, _) = render("12h", &config, at(21, 41, 7));
+        assert_snapshot("12h", &bitmap);
+    }
+
+    #[test]
+    fn without_seconds() {
+        let config = Config {
+            resolution: Resolution::Minutes,
+            ..plain()
+        };
+        let (bitmap, _) = render("minutes", &config, at(9, 41, 7));
+        assert_snapshot("minutes", &bitmap);
+    }
+
+    #[test]
+    fn significant_time_is_captioned() {
+        let (bitmap, flashes) = render("significant", &Config::default(), at(12, 34, 56));
+        assert_snapshot("significant", &bitmap);
+        assert_eq!(flashes, [FlashStyle::Single]);
+    }
+
+    #[test]
+    fn every_face() {
+        for face in Face::ALL {
+            let config = Config { face, ..plain() };
+            let name = format!("face-{face:?}").to_lowercase();
+            let (bitmap, _) = render(&name, &config, at(9, 41, 7));
+            assert_snapshot(&name, &bitmap);
+        }
+    }
+
+    #[test]
+    fn todays_count_shows_when_nothing_is_significant() {
+        let (bitmap, flashes) = render("count", &Config::default(), at(9, 41, 7));
+        assert_snapshot("count", &bitmap);
+        assert!(flashes.is_empty());
+    }
+
+    #[test]
+    fn badges_leave_the_face_alone() {
+        let config = Config {
+            hour_format: HourFormat::Twelve,
+            status: StatusConfig {
+                significance: true,
+                ..StatusConfig::default()
+            },
+            ..Config::default()
+        };
+        let (face, _) = render("badges", &config, at(21, 41, 7));
+        let now = Instant::now();
+        let mut status = Status::new(true, now);
+        for event in [
+            Event::NetworkConnected,
+            Event::ClockSynced,
+            Event::AlarmArmed,
+        ] {
+            status.update(&event, now);
+        }
+        let mut badged = face.clone();
+        status.draw(&config.status, now, &mut badged).unwrap();
+        assert_snapshot("badges", &badged);
+        for (x, y) in face.coordinates() {
+            assert!(
+                !face.get(x, y) || badged.get(x, y),
+                "A badge covers the face at ({x}, {y})"
+            );
+        }
+    }
+
+    #[test]
+    fn date_patterns_flash_once_a_minute() {
+        let history = env::temp_dir().join("significant-clock-date.json");
+        let _ = fs::remove_file(&history);
+        let config = Config::default();
+        let mut detector = Detector::new(&config, HistoryHandler::new(&history));
+        let (tx, rx) = bounded(64);
+        let day = NaiveDate::from_ymd_opt(2023, 11, 11).unwrap();
+        let start = day.and_hms_opt(11, 11, 0).unwrap();
+        for tick in 0..600 {
+            detector.detect(
+                &config,
+                &tx,
+                start + chrono::Duration::milliseconds(tick * 100),
+            );
+        }
+        assert_eq!(rx.try_iter().count(), 1);
+        assert_eq!(detector.history.current().count_on(day), 1);
+    }
+
+    #[test]
+    fn rules_can_be_chosen() {
+        let history = env::temp_dir().join("significant-clock-rules.json");
+        let significant = |rules: &[&str], dt| {
+            let _ = fs::remove_file(&history);
+            let config = Config {
+                rules: rules.iter().map(|name| name.to_string()).collect(),
+                ..Config::default()
+            };
+            let mut detector = Detector::new(&config, HistoryHandler::new(&history));
+            let (tx, _rx) = bounded(1);
+            detector.detect(&config, &tx, dt).fired
         };
-        delay.delay_ms(100);
+        assert!(significant(&["all-equal", "run", "mirror"], at(12, 34, 56)));
+        assert!(!significant(
+            &["all-equal", "run", "mirror"],
+            at(11, 22, 33)
+        ));
+        assert!(significant(&["doubles"], at(11, 22, 33)));
+        assert!(!significant(&["doubles"], at(12, 34, 56)));
+        // Unknown rules are skipped, leaving the rest.
+        assert!(significant(&["bogus", "doubles"], at(11, 22, 33)));
+        assert!(!significant(&[], at(12, 34, 56)));
+    }
+
+    /// What's lit on the panel, read back from the displays.
+    fn shown(chain: &Chain) -> Bitmap {
+        let layout = crate::screen_config();
+        let mut bitmap = Bitmap::new(layout.cols(), layout.rows());
+        for (x, y) in bitmap.coordinates() {
+            let (row_index, bit) = layout.position(x, y).unwrap();
+            bitmap.set(x, y, chain.lit(row_index / 8, row_index % 8, bit));
+        }
+        bitmap
+    }
+
+    #[test]
+    fn badges_follow_events_whoever_else_reads_them() {
+        let (display, chain) = mock::display(8);
+        let screen = ScreenBuilder::new(crate::screen_config())
+            .build(display)
+            .unwrap();
+        let history = env::temp_dir().join("significant-clock-badges.json");
+        let _ = fs::remove_file(&history);
+        let history = HistoryHandler::new(&history);
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let clock = FakeClock::new(utc.from_local_datetime(&at(9, 41, 7)).unwrap());
+
+        let mut bus = Bus::new(8);
+        // The config, lamp and main loops, which read every event too.
+        for _ in 0..3 {
+            let mut others = bus.add_rx();
+            thread::spawn(move || while others.recv().is_ok() {});
+        }
+        let rx = bus.add_rx();
+        let (flash_tx, _flash_rx) = bounded(4);
+        thread::spawn(move || screen_loop(screen, rx, flash_tx, plain(), history, clock));
+
+        let mut wifi = Bitmap::new(Badge::SIZE, Badge::SIZE);
+        Badge::Wifi.draw(Point::zero(), &mut wifi).unwrap();
+        let corner =
+            || shown(&chain.lock().unwrap()).crop(Point::zero(), Size::new_equal(Badge::SIZE));
+        // A burst of events is taken all at once, rather than one a frame, so it doesn't hold up
+        // the broadcast.
+        let burst = Instant::now();
+        for _ in 0..32 {
+            bus.broadcast(Event::ClockSynced);
+        }
+        assert!(burst.elapsed() < Duration::from_secs(1));
+        bus.broadcast(Event::NetworkConnected);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while corner() != wifi && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(corner(), wifi);
     }
 }
@@ -1,11 +1,25 @@
 use std::{
     fs::{self, File},
     path::Path,
+    time::{Duration, Instant},
 };
 
-use crate::{event::Event, leds::Pixel};
+use crate::{
+    animations::AnimationConfig, event::Event, faces::Face, leds::Pixel, message::MessageConfig,
+    status::StatusConfig,
+};
 use anyhow::Result;
-use crossbeam_channel::Receiver;
+use bus::BusReader;
+use logic::{
+    date::DateFormat,
+    format::{HourFormat, Resolution},
+    history::History,
+    maths::MathsRules,
+    rules::DEFAULT,
+    schedule::BrightnessSchedule,
+    significance::MATCH,
+    tracker::Cooldown,
+};
 use rgb::RGB8;
 use serde::{Deserialize, Serialize};
 
@@ -29,13 +43,49 @@ where
 }
 
 impl Persist<'_> for Config {}
+impl Persist<'_> for History {}
 
 /// Global clock config.  This is persisted to disk when modified, and can be set over the api.
+///
+/// Fields missing from a persisted config take their default, so new fields can be added without
+/// losing existing settings.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub lamp_on: bool,
     pub lamp_brightness: Pixel,
     pub significant_mode: bool,
+    /// Minimum interest score for a time to count as significant.  `MATCH` only accepts full
+    /// matches; lower values also accept increasingly faint near misses.
+    pub significance_threshold: u8,
+    /// 12- or 24-hour clock, for both display and significance.
+    pub hour_format: HourFormat,
+    /// Whether seconds are shown.  Without them significance uses the minute rules, and fires
+    /// once at the start of the minute.
+    pub resolution: Resolution,
+    /// Day/month order, for showing the date and for date patterns.
+    pub date_format: DateFormat,
+    /// How the time is shown.  A long press on the right button moves on to the next one.
+    pub face: Face,
+    /// Which built-in rules count as significant, by name and in priority order.  Unknown names
+    /// are logged and ignored.  With the default set, clocks without seconds use the minute rules.
+    pub rules: Vec<String>,
+    /// User-defined patterns, checked after the built-in rules.  See `logic::pattern` for the
+    /// syntax; invalid patterns are logged and ignored.
+    pub patterns: Vec<String>,
+    /// Which maths rules (primes, squares and so on) also count as significant.
+    pub maths: MathsRules,
+    /// How long each rule stays quiet after flashing, so a burst of neighbouring hits only
+    /// flashes once.
+    pub significance_cooldown: Cooldown,
+    /// Font, speed and so on for messages.
+    pub message: MessageConfig,
+    /// Transitions between faces, and the animation for significant moments.
+    pub animation: AnimationConfig,
+    /// Which badges show in the corners.
+    pub status: StatusConfig,
+    /// Dimming the screen and the lamp by time of day, and blanking the screen at night.
+    pub schedule: BrightnessSchedule,
 }
 
 impl Default for Config {
@@ -49,10 +99,29 @@ impl Default for Config {
             }
             .into(),
             significant_mode: true,
+            significance_threshold: MATCH,
+            hour_format: HourFormat::TwentyFour,
+            resolution: Resolution::Seconds,
+            date_format: DateFormat::Dmy,
+            face: Face::default(),
+            rules: default_rules(),
+            patterns: vec![],
+            maths: MathsRules::default(),
+            significance_cooldown: Cooldown::default(),
+            message: MessageConfig::default(),
+            animation: AnimationConfig::default(),
+            status: StatusConfig::default(),
+            schedule: BrightnessSchedule::default(),
         }
     }
 }
 
+/// The names of the default rule set.
+pub fn default_rules() -> Vec<String> {
+    DEFAULT.iter().map(|rule| rule.name().into()).collect()
+}
+
+#[allow(dead_code)] // TODO persisting lamp and alarm state.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct State {
     lamp_on: bool,
@@ -68,9 +137,15 @@ impl Default for State {
     }
 }
 
+/// Deferred changes are written out at most this often, to spare the flash.  Anything newer is
+/// lost if the power goes.
+const DEFER: Duration = Duration::from_secs(10 * 60);
+
 pub struct Handler<T> {
     current: T,
     path: Box<Path>,
+    /// When the oldest change not yet persisted was made, if there is one.
+    unsaved_since: Option<Instant>,
 }
 
 #[allow(dead_code)] // TODO set not used
@@ -84,6 +159,7 @@ where
         Self {
             current,
             path: path.into(),
+            unsaved_since: None,
         }
     }
 
@@ -91,8 +167,41 @@ where
         self.current.clone()
     }
 
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
     pub fn set(&mut self, val: T) {
         self.current = val;
+        self.persist();
+    }
+
+    /// Modify the current value in place, then persist it.
+    pub fn update(&mut self, f: impl FnOnce(&mut T)) {
+        f(&mut self.current);
+        self.persist();
+    }
+
+    /// Modify the current value in place, but only persist it once the change is `DEFER` old,
+    /// along with any others made since.  The value in memory is always up to date.
+    pub fn update_deferred(&mut self, now: Instant, f: impl FnOnce(&mut T)) {
+        f(&mut self.current);
+        self.unsaved_since.get_or_insert(now);
+        self.persist_due(now);
+    }
+
+    /// Persist deferred changes which have waited long enough.  Call this regularly.
+    pub fn persist_due(&mut self, now: Instant) {
+        if self
+            .unsaved_since
+            .is_some_and(|since| now.duration_since(since) >= DEFER)
+        {
+            self.persist();
+        }
+    }
+
+    fn persist(&mut self) {
+        self.unsaved_since = None;
         if let Err(e) = self.current.save(&self.path) {
             log::warn!("Error persisting: {e:?}");
         }
@@ -100,11 +209,50 @@ where
 }
 
 pub type ConfigHandler = Handler<Config>;
+pub type HistoryHandler = Handler<History>;
 
-pub fn config_loop(rx: Receiver<Event>, handler: &mut ConfigHandler) {
+pub fn config_loop(mut rx: BusReader<Event>, handler: &mut ConfigHandler) {
     loop {
         if let Ok(Event::ChangeConfig(config)) = rx.recv() {
             handler.set(config)
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        time::{Duration, Instant},
+    };
+
+    use chrono::NaiveDate;
+    use logic::history::History;
+
+    use crate::config::{HistoryHandler, Persist, DEFER};
+
+    #[test]
+    fn deferred_updates_are_persisted_together() {
+        let path = env::temp_dir().join("significant-clock-deferred.json");
+        let _ = fs::remove_file(&path);
+        let mut handler = HistoryHandler::new(&path);
+        let day = NaiveDate::from_ymd_opt(2023, 11, 14).unwrap();
+        let moment = |h, m, s| day.and_hms_opt(h, m, s).unwrap();
+        let start = Instant::now();
+
+        handler.update_deferred(start, |history| history.record(moment(12, 34, 56), "run"));
+        let later = start + Duration::from_secs(60);
+        handler.update_deferred(later, |history| history.record(moment(12, 35, 0), "run"));
+        handler.persist_due(start + DEFER - Duration::from_secs(1));
+        assert_eq!(handler.current().count_on(day), 2);
+        assert!(!path.exists());
+
+        handler.persist_due(start + DEFER);
+        assert_eq!(History::load(&path).count_on(day), 2);
+
+        // Nothing's written again until there's something new.
+        fs::remove_file(&path).unwrap();
+        handler.persist_due(later + DEFER);
+        assert!(!path.exists());
+    }
+}
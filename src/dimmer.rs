@@ -0,0 +1,181 @@
+//! Follows the brightness schedule in `Config`.
+//!
+//! The screen and the lamp each keep a [`Dimmer`] and apply whatever it reports, so neither
+//! depends on the other getting an event through.
+use chrono::{DateTime, Duration, DurationRound, FixedOffset, Local, NaiveDateTime};
+use logic::schedule::{BrightnessSchedule, Setting};
+
+/// Where the dimmer gets the time from, so it can be tested without waiting for nightfall.
+pub trait Clock {
+    fn now(&self) -> DateTime<FixedOffset>;
+}
+
+/// Local time from the system clock, in whatever timezone the screen loop sets.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        Local::now().fixed_offset()
+    }
+}
+
+impl<C: Clock> Clock for &C {
+    fn now(&self) -> DateTime<FixedOffset> {
+        (*self).now()
+    }
+}
+
+/// A clock which only moves when it's told to, shared between its clones.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct FakeClock(std::sync::Arc<std::sync::Mutex<DateTime<FixedOffset>>>);
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new(now: DateTime<FixedOffset>) -> FakeClock {
+        FakeClock(std::sync::Arc::new(std::sync::Mutex::new(now)))
+    }
+
+    pub fn set(&self, now: DateTime<FixedOffset>) {
+        *self.0.lock().unwrap() = now;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Reports changes in what the schedule calls for.
+pub struct Dimmer<C: Clock> {
+    clock: C,
+    schedule: BrightnessSchedule,
+    current: Option<Setting>,
+    /// The minute last checked.
+    checked: Option<NaiveDateTime>,
+}
+
+impl<C: Clock> Dimmer<C> {
+    pub fn new(clock: C, schedule: BrightnessSchedule) -> Dimmer<C> {
+        Dimmer {
+            clock,
+            schedule,
+            current: None,
+            checked: None,
+        }
+    }
+
+    /// Follow `schedule` from the next poll.
+    pub fn reconfigure(&mut self, schedule: &BrightnessSchedule) {
+        if *schedule != self.schedule {
+            self.schedule = schedule.clone();
+            self.checked = None;
+        }
+    }
+
+    /// What the schedule calls for now, if it's changed since the last poll (or this is the
+    /// first).  The schedule is worked out at most once a minute, so this is cheap to call every
+    /// frame.
+    pub fn poll(&mut self) -> Option<Setting> {
+        let now = self.clock.now();
+        let minute = now.naive_local().duration_trunc(Duration::minutes(1)).ok();
+        if minute.is_some() && minute == self.checked {
+            return None;
+        }
+        self.checked = minute;
+        let setting = self.schedule.at(&now);
+        (self.current.replace(setting) != Some(setting)).then_some(setting)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone};
+    use logic::schedule::{BrightnessSchedule, Level, Setting, TimeOfDay, Window};
+
+    use crate::dimmer::{Dimmer, FakeClock};
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2023, 11, 14, h, m, s)
+            .unwrap()
+    }
+
+    fn time(h: u32, m: u32) -> TimeOfDay {
+        TimeOfDay::At(NaiveTime::from_hms_opt(h, m, 0).unwrap())
+    }
+
+    const DAY: Level = Level {
+        screen: 8,
+        lamp: 100,
+    };
+    const NIGHT: Level = Level {
+        screen: 1,
+        lamp: 10,
+    };
+
+    fn schedule() -> BrightnessSchedule {
+        BrightnessSchedule {
+            enabled: true,
+            day: Window {
+                from: time(7, 0),
+                until: time(22, 0),
+            },
+            day_level: DAY,
+            night_level: NIGHT,
+            blank: Some(Window {
+                from: time(23, 0),
+                until: time(6, 0),
+            }),
+            ..BrightnessSchedule::default()
+        }
+    }
+
+    fn setting(level: Level, blank: bool) -> Option<Setting> {
+        Some(Setting { level, blank })
+    }
+
+    #[test]
+    fn reports_changes() {
+        let clock = FakeClock::new(at(21, 58, 0));
+        let mut dimmer = Dimmer::new(clock.clone(), schedule());
+        assert_eq!(dimmer.poll(), setting(DAY, false));
+        assert_eq!(dimmer.poll(), None);
+        clock.set(at(21, 59, 59));
+        assert_eq!(dimmer.poll(), None);
+        clock.set(at(22, 0, 0));
+        assert_eq!(dimmer.poll(), setting(NIGHT, false));
+        clock.set(at(22, 30, 0));
+        assert_eq!(dimmer.poll(), None);
+        clock.set(at(23, 0, 0));
+        assert_eq!(dimmer.poll(), setting(NIGHT, true));
+        clock.set(at(6, 0, 0));
+        assert_eq!(dimmer.poll(), setting(NIGHT, false));
+        clock.set(at(7, 0, 0));
+        assert_eq!(dimmer.poll(), setting(DAY, false));
+    }
+
+    #[test]
+    fn reconfiguring_applies_straight_away() {
+        let clock = FakeClock::new(at(12, 0, 0));
+        let mut dimmer = Dimmer::new(clock.clone(), schedule());
+        assert_eq!(dimmer.poll(), setting(DAY, false));
+
+        // Only a change in the setting is reported.
+        dimmer.reconfigure(&BrightnessSchedule {
+            blank: None,
+            ..schedule()
+        });
+        assert_eq!(dimmer.poll(), None);
+
+        let brighter = Level { screen: 15, ..DAY };
+        dimmer.reconfigure(&BrightnessSchedule {
+            day_level: brighter,
+            ..schedule()
+        });
+        assert_eq!(dimmer.poll(), setting(brighter, false));
+    }
+}
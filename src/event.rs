@@ -1,7 +1,7 @@
 use crate::config::Config;
 
 #[allow(dead_code)] // TODO working out what granularity to use.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Event {
     // Network
     APActivated,
@@ -10,6 +10,9 @@ pub enum Event {
     NetworkConnected,
     // NTP
     ClockSynced,
+    // alarm
+    AlarmArmed,
+    AlarmDisarmed,
     // display
     ChangeBrightness(u8),
     ShowStatic(String),
@@ -17,6 +20,4 @@ pub enum Event {
     Show,
     // clock
     ChangeConfig(Config),
-    // Internal
-    Flash,
 }
@@ -0,0 +1,625 @@
+//! Clock faces: the different ways the screen can show the time.
+//!
+//! Each face draws onto any 1-bit [`DrawTarget`], so they can be previewed on a
+//! [`Bitmap`](matrix::bitmap::Bitmap) as well as shown on the screen.
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{NaiveDateTime, Timelike};
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use logic::format::{HourFormat, Resolution};
+use matrix::font::{self, Badge, Font};
+use serde::{Deserialize, Serialize};
+use u8g2_fonts::{
+    fonts,
+    types::{FontColor, HorizontalAlignment, VerticalPosition},
+    FontRenderer,
+};
+
+use crate::config::Config;
+
+/// A way of showing the time.
+pub trait ClockFace {
+    /// Draw `dt`, local time, onto `target`, which should already be blank.
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug;
+}
+
+/// Which face is shown, as set in the config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Face {
+    #[default]
+    Digital,
+    Tall,
+    Binary,
+    Bcd,
+    Words,
+    Roman,
+    Progress,
+}
+
+impl Face {
+    pub const ALL: [Face; 7] = [
+        Face::Digital,
+        Face::Tall,
+        Face::Binary,
+        Face::Bcd,
+        Face::Words,
+        Face::Roman,
+        Face::Progress,
+    ];
+
+    /// The face after this one, wrapping round, for cycling with a button.
+    pub fn next(self) -> Face {
+        let i = Face::ALL.iter().position(|face| *face == self).unwrap_or(0);
+        Face::ALL[(i + 1) % Face::ALL.len()]
+    }
+
+    /// Draw `dt` with this face, formatted as `config` says.  Only the digital face has room for
+    /// `caption`.
+    pub fn draw<D>(
+        self,
+        config: &Config,
+        dt: &NaiveDateTime,
+        caption: Caption,
+        target: &mut D,
+    ) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        let (hour_format, resolution) = (config.hour_format, config.resolution);
+        match self {
+            Face::Digital => Digital {
+                hour_format,
+                resolution,
+                caption,
+            }
+            .draw(dt, target),
+            Face::Tall => Tall { hour_format }.draw(dt, target),
+            Face::Binary => Binary {
+                hour_format,
+                resolution,
+            }
+            .draw(dt, target),
+            Face::Bcd => Bcd {
+                hour_format,
+                resolution,
+            }
+            .draw(dt, target),
+            Face::Words => Words.draw(dt, target),
+            Face::Roman => Roman { hour_format }.draw(dt, target),
+            Face::Progress => Progress { hour_format }.draw(dt, target),
+        }
+    }
+}
+
+/// Small print across the top of the digital face.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Caption {
+    #[default]
+    None,
+    /// Like what made the time significant.
+    Label(String),
+    /// Like how many significant moments there have been today.
+    Count(u32),
+}
+
+fn large_font() -> FontRenderer {
+    FontRenderer::new::<fonts::u8g2_font_5x7_tf>()
+}
+
+fn tiny_font() -> FontRenderer {
+    FontRenderer::new::<fonts::u8g2_font_u8glib_4_tf>()
+}
+
+/// Draw `text` in `font`, aligned on `position`.
+fn text<D>(
+    target: &mut D,
+    font: &FontRenderer,
+    text: &str,
+    position: Point,
+    vertical: VerticalPosition,
+    horizontal: HorizontalAlignment,
+) -> Result<()>
+where
+    D: DrawTarget<Color = BinaryColor>,
+    D::Error: Debug,
+{
+    font.render_aligned(
+        text,
+        position,
+        vertical,
+        horizontal,
+        FontColor::Transparent(BinaryColor::On),
+        target,
+    )
+    .map_err(|e| anyhow!("Can't render '{text}': {e:?}"))?;
+    Ok(())
+}
+
+/// Draw `text` in one of the matrix's own fonts, centred horizontally with its top at `y`.
+fn matrix_text<D>(target: &mut D, font: &Font, text: &str, y: i32) -> Result<()>
+where
+    D: DrawTarget<Color = BinaryColor>,
+    D::Error: Debug,
+{
+    let x = centred(target, Size::new(font.width(text), 0)).x;
+    font.draw(text, Point::new(x, y), target)
+        .map_err(|e| anyhow!("Can't draw '{text}': {e:?}"))?;
+    Ok(())
+}
+
+/// Light the `size` rectangle with its top left at `top_left`.
+fn fill<D>(target: &mut D, top_left: Point, size: Size) -> Result<()>
+where
+    D: DrawTarget<Color = BinaryColor>,
+    D::Error: Debug,
+{
+    Rectangle::new(top_left, size)
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(target)
+        .map_err(|e| anyhow!("Can't draw: {e:?}"))
+}
+
+fn dot<D>(target: &mut D, point: Point) -> Result<()>
+where
+    D: DrawTarget<Color = BinaryColor>,
+    D::Error: Debug,
+{
+    Pixel(point, BinaryColor::On)
+        .draw(target)
+        .map_err(|e| anyhow!("Can't draw: {e:?}"))
+}
+
+/// Where to put something `size` so it's centred on `target`.
+fn centred<D: Dimensions>(target: &D, size: Size) -> Point {
+    let bounds = target.bounding_box();
+    bounds.top_left + (bounds.size.saturating_sub(size) / 2)
+}
+
+/// Hours, minutes and, at `Resolution::Seconds`, seconds.
+fn fields(hour_format: HourFormat, resolution: Resolution, dt: &NaiveDateTime) -> Vec<u32> {
+    let time = hour_format.displayed(dt);
+    match resolution {
+        Resolution::Seconds => vec![time.hour(), time.minute(), time.second()],
+        Resolution::Minutes => vec![time.hour(), time.minute()],
+    }
+}
+
+/// How far the digital face's small print keeps from the sides, leaving the corners to the status
+/// badges: a badge's width and the pixel cleared beside it.
+const BADGE_CLEARANCE: i32 = Badge::SIZE as i32 + 1;
+
+/// Hours and minutes in the middle, with seconds, AM/PM and a caption around them in small print.
+/// The corners are left clear for the status badges.
+pub struct Digital {
+    pub hour_format: HourFormat,
+    pub resolution: Resolution,
+    pub caption: Caption,
+}
+
+impl ClockFace for Digital {
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        let bounds = target.bounding_box();
+        let hm = dt.format(self.hour_format.hour_minute()).to_string();
+        text(
+            target,
+            &large_font(),
+            &hm,
+            bounds.center(),
+            VerticalPosition::Center,
+            HorizontalAlignment::Center,
+        )?;
+        let bottom_rhc = bounds.bottom_right().context("Face has no bottom rhc")?;
+        let tiny = tiny_font();
+
+        if self.resolution == Resolution::Seconds {
+            text(
+                target,
+                &tiny,
+                &dt.format("%S").to_string(),
+                bottom_rhc + Point::new(1 - BADGE_CLEARANCE, 2),
+                VerticalPosition::Bottom,
+                HorizontalAlignment::Right,
+            )?;
+        }
+
+        if self.hour_format == HourFormat::Twelve {
+            text(
+                target,
+                &tiny,
+                &dt.format("%p").to_string(),
+                Point::new(bounds.top_left.x + BADGE_CLEARANCE, bottom_rhc.y + 2),
+                VerticalPosition::Bottom,
+                HorizontalAlignment::Left,
+            )?;
+        }
+
+        // The font leaves a row above its digits, which the count doesn't need: without it the
+        // count stays clear of the time below.
+        let (caption, raised) = match &self.caption {
+            Caption::None => return Ok(()),
+            Caption::Label(label) => (label.clone(), 0),
+            Caption::Count(count) => (count.to_string(), 1),
+        };
+        text(
+            target,
+            &tiny,
+            &caption,
+            Point::new(bounds.center().x, bounds.top_left.y - raised),
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+        )
+    }
+}
+
+/// Hours and minutes in digits as high as the screen.
+pub struct Tall {
+    pub hour_format: HourFormat,
+}
+
+impl ClockFace for Tall {
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        let hm = dt.format(self.hour_format.hour_minute()).to_string();
+        let top = centred(target, Size::new(0, font::TALL.height())).y;
+        matrix_text(target, &font::TALL, &hm, top)
+    }
+}
+
+/// A row of six bits each for hours, minutes and seconds, most significant on the left.  Set bits
+/// are filled squares; clear ones a dot, so the places can still be counted.
+pub struct Binary {
+    pub hour_format: HourFormat,
+    pub resolution: Resolution,
+}
+
+impl ClockFace for Binary {
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        const BITS: u32 = 6;
+        const CELL: u32 = 4;
+        const PITCH: u32 = CELL + 1;
+        let fields = fields(self.hour_format, self.resolution, dt);
+        let size = Size::new(BITS * PITCH - 1, fields.len() as u32 * PITCH - 1);
+        let origin = centred(target, size);
+        for (row, value) in fields.iter().enumerate() {
+            for bit in 0..BITS {
+                let cell = origin + Point::new((bit * PITCH) as i32, (row as u32 * PITCH) as i32);
+                if (value >> (BITS - 1 - bit)) & 1 == 1 {
+                    fill(target, cell, Size::new(CELL, CELL))?;
+                } else {
+                    dot(target, cell + Point::new(1, 1))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Binary-coded decimal: a column of four bits for each digit, 8 at the top, in pairs for hours,
+/// minutes and seconds.
+pub struct Bcd {
+    pub hour_format: HourFormat,
+    pub resolution: Resolution,
+}
+
+impl ClockFace for Bcd {
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        const BITS: u32 = 4;
+        const CELL: u32 = 3;
+        const PITCH: u32 = CELL + 1;
+        const PAIR_WIDTH: u32 = 2 * PITCH - 1;
+        // Pairs are a column apart.
+        const PAIR_PITCH: u32 = PAIR_WIDTH + PITCH;
+        let fields = fields(self.hour_format, self.resolution, dt);
+        let size = Size::new(fields.len() as u32 * PAIR_PITCH - PITCH, BITS * PITCH - 1);
+        let origin = centred(target, size);
+        for (pair, value) in fields.iter().enumerate() {
+            for (column, digit) in [value / 10, value % 10].into_iter().enumerate() {
+                let x = pair as u32 * PAIR_PITCH + column as u32 * PITCH;
+                for bit in 0..BITS {
+                    let cell = origin + Point::new(x as i32, (bit * PITCH) as i32);
+                    if (digit >> (BITS - 1 - bit)) & 1 == 1 {
+                        fill(target, cell, Size::new(CELL, CELL))?;
+                    } else {
+                        dot(target, cell + Point::new(1, 1))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const HOURS: [&str; 12] = [
+    "TWELVE", "ONE", "TWO", "THREE", "FOUR", "FIVE", "SIX", "SEVEN", "EIGHT", "NINE", "TEN",
+    "ELEVEN",
+];
+
+/// The time to the nearest five minutes in words, one line each.  Lines are kept to seven letters
+/// to fit across the screen, hence the British "five to half seven" for 7:25.
+pub fn phrase(hour: u32, minute: u32) -> Vec<&'static str> {
+    let rounded = (minute + 2) / 5 * 5;
+    let hour = |offset: u32| HOURS[((hour + offset) % 12) as usize];
+    match rounded {
+        0 => vec![hour(0), "O'CLOCK"],
+        5 => vec!["FIVE", "PAST", hour(0)],
+        10 => vec!["TEN", "PAST", hour(0)],
+        15 => vec!["QUARTER", "PAST", hour(0)],
+        20 => vec!["TWENTY", "PAST", hour(0)],
+        25 => vec!["FIVE TO", "HALF", hour(0)],
+        30 => vec!["HALF", "PAST", hour(0)],
+        35 => vec!["TWENTY", "FIVE TO", hour(1)],
+        40 => vec!["TWENTY", "TO", hour(1)],
+        45 => vec!["QUARTER", "TO", hour(1)],
+        50 => vec!["TEN", "TO", hour(1)],
+        55 => vec!["FIVE", "TO", hour(1)],
+        _ => vec![hour(1), "O'CLOCK"],
+    }
+}
+
+/// A word clock: the time to the nearest five minutes, spelt out.
+pub struct Words;
+
+impl ClockFace for Words {
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        const PITCH: i32 = 5;
+        let lines = phrase(dt.hour(), dt.minute());
+        let bounds = target.bounding_box();
+        let top = centred(target, Size::new(0, lines.len() as u32 * PITCH as u32 - 1)).y;
+        let tiny = tiny_font();
+        for (i, line) in lines.iter().enumerate() {
+            text(
+                target,
+                &tiny,
+                line,
+                Point::new(bounds.center().x, top + i as i32 * PITCH),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `n` in Roman numerals.  The Romans had no zero, so that's N, for *nulla*.
+pub fn roman(mut n: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    if n == 0 {
+        return "N".into();
+    }
+    let mut numerals = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            numerals.push_str(numeral);
+            n -= value;
+        }
+    }
+    numerals
+}
+
+/// Hours in Roman numerals across the top, and minutes in smaller ones beneath.
+pub struct Roman {
+    pub hour_format: HourFormat,
+}
+
+impl ClockFace for Roman {
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        let time = self.hour_format.displayed(dt);
+        let bounds = target.bounding_box();
+        let bottom = bounds.bottom_right().context("Face has no bottom rhc")?.y;
+        text(
+            target,
+            &large_font(),
+            &roman(time.hour()),
+            Point::new(bounds.center().x, bounds.top_left.y),
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+        )?;
+        text(
+            target,
+            &tiny_font(),
+            &roman(time.minute()),
+            Point::new(bounds.center().x, bottom + 2),
+            VerticalPosition::Bottom,
+            HorizontalAlignment::Center,
+        )
+    }
+}
+
+/// Hours and minutes along the top row of modules, over a bar which fills up as the minute goes
+/// by.
+pub struct Progress {
+    pub hour_format: HourFormat,
+}
+
+impl ClockFace for Progress {
+    fn draw<D>(&self, dt: &NaiveDateTime, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        const BAR: u32 = 2;
+        let bounds = target.bounding_box();
+        let hm = dt.format(self.hour_format.hour_minute()).to_string();
+        matrix_text(target, &font::SMALL, &hm, bounds.top_left.y)?;
+        let length = bounds.size.width * (dt.second() + 1) / 60;
+        let bar_top = bounds.top_left + Point::new(0, (bounds.size.height - BAR) as i32);
+        if length > 0 {
+            fill(target, bar_top, Size::new(length, BAR))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use embedded_graphics::prelude::*;
+    use logic::format::{HourFormat, Resolution};
+    use matrix::{bitmap::Bitmap, font};
+
+    use crate::faces::{phrase, roman, Bcd, Binary, ClockFace, Face, Tall};
+
+    fn at(h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 11, 14)
+            .unwrap()
+            .and_hms_opt(h, m, s)
+            .unwrap()
+    }
+
+    fn drawn(face: impl ClockFace, dt: NaiveDateTime) -> Bitmap {
+        let mut bitmap = Bitmap::new(32, 16);
+        face.draw(&dt, &mut bitmap).unwrap();
+        bitmap
+    }
+
+    #[test]
+    fn faces_cycle_through_them_all() {
+        let mut face = Face::default();
+        let mut seen = vec![];
+        for _ in Face::ALL {
+            seen.push(face);
+            face = face.next();
+        }
+        assert_eq!(seen, Face::ALL);
+        assert_eq!(face, Face::default());
+    }
+
+    #[test]
+    fn tall_digits_are_centred() {
+        let face = Tall {
+            hour_format: HourFormat::TwentyFour,
+        };
+        let bitmap = drawn(face, at(20, 8, 0));
+        let hm = font::TALL.bitmap("20:08");
+        assert_eq!(hm.width(), 30);
+        assert_eq!(bitmap.crop(Point::new(1, 0), hm.size()), hm);
+    }
+
+    #[test]
+    fn binary_rows_are_hours_minutes_and_seconds() {
+        let face = Binary {
+            hour_format: HourFormat::TwentyFour,
+            resolution: Resolution::Seconds,
+        };
+        // 12 = 001100, 34 = 100010, 56 = 111000.
+        let expected = Bitmap::from_ascii(
+            "
+            ................................
+            ...........####.####............
+            ..#....#...####.####..#....#....
+            ...........####.####............
+            ...........####.####............
+            ................................
+            .####................####.......
+            .####..#....#....#...####..#....
+            .####................####.......
+            .####................####.......
+            ................................
+            .####.####.####.................
+            .####.####.####..#....#....#....
+            .####.####.####.................
+            .####.####.####.................
+            ................................
+            ",
+        )
+        .unwrap();
+        assert_eq!(drawn(face, at(12, 34, 56)), expected);
+    }
+
+    #[test]
+    fn bcd_columns_are_digits() {
+        let face = Bcd {
+            hour_format: HourFormat::TwentyFour,
+            resolution: Resolution::Minutes,
+        };
+        // 0 9 : 4 1, eights at the top.
+        let expected = Bitmap::from_ascii(
+            "
+            ...........###..................
+            ........#..###.....#...#........
+            ...........###..................
+            ................................
+            ..................###...........
+            ........#...#.....###..#........
+            ..................###...........
+            ................................
+            ................................
+            ........#...#......#...#........
+            ................................
+            ................................
+            ...........###........###.......
+            ........#..###.....#..###.......
+            ...........###........###.......
+            ................................
+            ",
+        )
+        .unwrap();
+        assert_eq!(drawn(face, at(9, 41, 7)), expected);
+    }
+
+    #[test]
+    fn words_round_to_five_minutes() {
+        assert_eq!(phrase(7, 0), ["SEVEN", "O'CLOCK"]);
+        assert_eq!(phrase(7, 13), ["QUARTER", "PAST", "SEVEN"]);
+        assert_eq!(phrase(7, 26), ["FIVE TO", "HALF", "SEVEN"]);
+        assert_eq!(phrase(7, 35), ["TWENTY", "FIVE TO", "EIGHT"]);
+        assert_eq!(phrase(23, 50), ["TEN", "TO", "TWELVE"]);
+        assert_eq!(phrase(23, 58), ["TWELVE", "O'CLOCK"]);
+    }
+
+    #[test]
+    fn roman_numerals() {
+        let numerals: Vec<String> = [0, 4, 9, 14, 23, 40, 49, 59].map(roman).into();
+        assert_eq!(
+            numerals,
+            ["N", "IV", "IX", "XIV", "XXIII", "XL", "XLIX", "LIX"]
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! The few platform services used outside `main`, so the app also builds for the host under the
+//! `sim` feature.
+#[cfg(feature = "esp")]
+pub use esp_idf_hal::delay::{Delay, Ets};
+
+#[cfg(feature = "sim")]
+pub use crate::sim::{Delay, Ets};
+
+/// Stack size for each task.  Host threads need more, as debug builds aren't size-optimised.
+#[cfg(feature = "esp")]
+pub const STACK_SIZE: usize = 4096;
+#[cfg(feature = "sim")]
+pub const STACK_SIZE: usize = 256 * 1024;
+
+/// Set the timezone used by `chrono::Local`, as a POSIX `TZ` string.
+#[cfg(feature = "esp")]
+pub fn set_timezone(zone: &str) {
+    use esp_idf_hal::sys::{setenv, tzset};
+    use std::ffi::CString;
+
+    let tz = CString::new("TZ").expect("Unable to generate 'TZ' as string");
+    let zone = CString::new(zone).expect("Unable to generate timezone string");
+    unsafe {
+        setenv(tz.as_ptr(), zone.as_ptr(), 1);
+        tzset();
+    }
+}
+
+#[cfg(feature = "sim")]
+pub fn set_timezone(zone: &str) {
+    std::env::set_var("TZ", zone);
+}
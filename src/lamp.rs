@@ -1,20 +1,52 @@
-use crossbeam_channel::Receiver;
+use std::time::Duration;
+
+use bus::BusReader;
+use crossbeam_channel::{Receiver, RecvTimeoutError};
 use embedded_hal::pwm::SetDutyCycle;
+use logic::rules::FlashStyle;
+use rgb::RGB8;
+
+use crate::{
+    config::Config,
+    dimmer::{Clock, Dimmer, SystemClock},
+    event::Event,
+    leds::{Leds, Pixel},
+};
 
-use crate::{config::Config, event::Event, leds::Leds};
+/// How long a flash can wait, and so how often events and the schedule are checked when there's
+/// nothing to flash.
+const POLL: Duration = Duration::from_millis(100);
 
-pub struct Lamp<T: SetDutyCycle> {
+pub struct Lamp<T: SetDutyCycle, C: Clock = SystemClock> {
     leds: Leds<T>,
     config: Config,
+    dimmer: Dimmer<C>,
+    /// Percent of `config.lamp_brightness`, as the schedule calls for.
+    level: u8,
 }
 
 impl<T: SetDutyCycle> Lamp<T> {
     pub fn new(leds: Leds<T>, config: Config) -> Lamp<T> {
-        Lamp { leds, config }
+        Lamp::with_clock(leds, config, SystemClock)
+    }
+}
+
+impl<T: SetDutyCycle, C: Clock> Lamp<T, C> {
+    /// A lamp following the schedule by `clock`.
+    pub fn with_clock(leds: Leds<T>, config: Config, clock: C) -> Lamp<T, C> {
+        let mut dimmer = Dimmer::new(clock, config.schedule.clone());
+        let level = dimmer.poll().map_or(100, |setting| setting.level.lamp);
+        Lamp {
+            leds,
+            config,
+            dimmer,
+            level,
+        }
     }
 
     fn on(&mut self) -> Result<(), T::Error> {
-        self.leds.fade(self.config.lamp_brightness)
+        self.leds
+            .fade(self.config.lamp_brightness.scaled(self.level))
     }
 
     fn off(&mut self) -> Result<(), T::Error> {
@@ -29,18 +61,185 @@ impl<T: SetDutyCycle> Lamp<T> {
         }
     }
 
-    pub fn run(&mut self, rx: Receiver<Event>) -> ! {
+    fn follow_schedule(&mut self) {
+        if let Some(setting) = self.dimmer.poll() {
+            self.level = setting.level.lamp;
+            let _ = self.sync();
+        }
+    }
+
+    /// Flash white, dimmed along with the lamp so it doesn't dazzle at night.
+    fn flash(&mut self, style: FlashStyle) -> Result<(), T::Error> {
+        let white: Pixel = RGB8::new(255, 255, 255).into();
+        self.leds.flash(style, white.scaled(self.level))
+    }
+
+    /// Flash whatever arrives on `flashes`, which is the lamp's alone, and follow config changes
+    /// from `rx`.
+    pub fn run(&mut self, mut rx: BusReader<Event>, flashes: Receiver<FlashStyle>) -> ! {
         loop {
-            match rx.recv() {
-                Ok(Event::Flash) => {
-                    let _ = self.leds.flash();
+            self.follow_schedule();
+            match flashes.recv_timeout(POLL) {
+                Ok(style) => {
+                    let _ = self.flash(style);
                 }
-                Ok(Event::ChangeConfig(config)) => {
+                Err(RecvTimeoutError::Timeout) => (),
+                // Nothing will flash again, but the lamp still follows its config.
+                Err(RecvTimeoutError::Disconnected) => std::thread::sleep(POLL),
+            }
+            while let Ok(event) = rx.try_recv() {
+                if let Event::ChangeConfig(config) = event {
+                    self.dimmer.reconfigure(&config.schedule);
                     self.config = config;
                     let _ = self.sync();
                 }
-                _ => (),
             }
         }
     }
 }
+
+#[cfg(all(test, feature = "sim"))]
+mod tests {
+    use std::{
+        convert::Infallible,
+        env, fs,
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use bus::Bus;
+    use chrono::{DateTime, FixedOffset, TimeZone};
+    use crossbeam_channel::bounded;
+    use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+    use logic::{rules::FlashStyle, schedule::BrightnessSchedule};
+    use matrix::{mock, screen::ScreenBuilder};
+
+    use crate::{
+        clock::screen_loop,
+        config::{Config, HistoryHandler},
+        dimmer::FakeClock,
+        event::Event,
+        lamp::Lamp,
+        leds::Leds,
+    };
+
+    /// A colour channel which remembers every duty cycle it's set to.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<u16>>>);
+
+    impl ErrorType for Recorder {
+        type Error = Infallible;
+    }
+
+    impl SetDutyCycle for Recorder {
+        fn get_max_duty_cycle(&self) -> u16 {
+            u8::MAX.into()
+        }
+
+        fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+            self.0.lock().unwrap().push(duty);
+            Ok(())
+        }
+    }
+
+    impl Recorder {
+        /// How many times the channel has come up to full.
+        fn flashes(&self) -> usize {
+            let duties = self.0.lock().unwrap();
+            duties
+                .windows(2)
+                .filter(|pair| pair[0] < 255 && pair[1] == 255)
+                .count()
+        }
+
+        /// The highest the channel has been set.
+        fn brightest(&self) -> u16 {
+            self.0.lock().unwrap().iter().copied().max().unwrap_or(0)
+        }
+
+        /// Wait for `expected` flashes, then a little longer to be sure there are no more.
+        fn assert_flashes(&self, expected: usize) {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while self.flashes() < expected && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            thread::sleep(Duration::from_millis(500));
+            assert_eq!(self.flashes(), expected);
+        }
+    }
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2023, 11, 14, h, m, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn flashes_reach_the_lamp_whoever_reads_the_events() {
+        let (display, _) = mock::display(8);
+        let screen = ScreenBuilder::new(crate::screen_config())
+            .build(display)
+            .unwrap();
+        let history = env::temp_dir().join("significant-clock-lamp.json");
+        let _ = fs::remove_file(&history);
+        let clock = FakeClock::new(at(12, 34, 56));
+
+        let mut bus = Bus::<Event>::new(8);
+        let (flash_tx, flash_rx) = bounded(4);
+
+        // The other tasks reading events, which mustn't get in the way.
+        let mut others = bus.add_rx();
+        thread::spawn(move || while others.recv().is_ok() {});
+
+        {
+            let clock = clock.clone();
+            let rx = bus.add_rx();
+            let history = HistoryHandler::new(&history);
+            thread::spawn(move || {
+                screen_loop(screen, rx, flash_tx, Config::default(), history, clock)
+            });
+        }
+        let red = Recorder::default();
+        {
+            let leds = Leds::new(red.clone(), Recorder::default(), Recorder::default());
+            let mut lamp = Lamp::new(leds, Config::default());
+            let rx = bus.add_rx();
+            thread::spawn(move || lamp.run(rx, flash_rx));
+        }
+        for _ in 0..16 {
+            bus.broadcast(Event::ClockSynced);
+        }
+
+        // A run flashes once, however many times it's redrawn.
+        red.assert_flashes(1);
+        // All the same digits flash three times.
+        clock.set(at(22, 22, 22));
+        red.assert_flashes(4);
+    }
+
+    #[test]
+    fn flashes_are_dimmed_with_the_lamp() {
+        let config = Config {
+            schedule: BrightnessSchedule {
+                enabled: true,
+                ..BrightnessSchedule::default()
+            },
+            ..Config::default()
+        };
+        let clock = FakeClock::new(at(23, 0, 0));
+        let red = Recorder::default();
+        let leds = Leds::new(red.clone(), Recorder::default(), Recorder::default());
+        let mut lamp = Lamp::with_clock(leds, config, clock.clone());
+
+        // The night level is 20%.
+        lamp.flash(FlashStyle::Single).unwrap();
+        assert_eq!(red.brightest(), 51);
+
+        clock.set(at(12, 0, 0));
+        lamp.follow_schedule();
+        lamp.flash(FlashStyle::Single).unwrap();
+        assert_eq!(red.brightest(), 255);
+    }
+}
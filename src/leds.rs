@@ -1,6 +1,7 @@
+use crate::hal::Ets;
 use anyhow::Result;
 use embedded_hal::pwm::SetDutyCycle;
-use esp_idf_hal::delay::Ets;
+use logic::rules::FlashStyle;
 use rgb::{RGB, RGB8};
 use serde::{Deserialize, Serialize};
 use std::ops::{Deref, DerefMut};
@@ -52,6 +53,17 @@ impl Pixel {
         let b = self.b.saturating_sub(other.b);
         RGB8 { r, g, b }.into()
     }
+
+    /// This colour at `percent` of its brightness.
+    pub fn scaled(self, percent: u8) -> Self {
+        let scale = |c: u8| (c as u16 * percent.min(100) as u16 / 100) as u8;
+        RGB8 {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b),
+        }
+        .into()
+    }
 }
 
 pub struct Leds<T: SetDutyCycle> {
@@ -117,22 +129,12 @@ impl<T: SetDutyCycle> Leds<T> {
         self.fade(RGB8 { r: 0, g: 0, b: 0 }.into())
     }
 
-    pub fn on(&mut self) -> Result<(), T::Error> {
-        self.fade(
-            RGB8 {
-                r: 255,
-                g: 255,
-                b: 255,
-            }
-            .into(),
-        )
-    }
-
-    pub fn flash(&mut self) -> Result<(), T::Error> {
+    /// Flash up to `peak` as many times as `style` says, then fade back to what was showing.
+    pub fn flash(&mut self, style: FlashStyle, peak: Pixel) -> Result<(), T::Error> {
         let current = self.current;
-        for _ in 0..2 {
+        for _ in 0..style.count() {
             self.off()?;
-            self.on()?;
+            self.fade(peak)?;
         }
         self.fade(current)?;
         Ok(())
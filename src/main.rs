@@ -1,41 +1,149 @@
 #![feature(never_type)]
 
-use anyhow::{Context, Result};
-use crossbeam_channel::bounded;
-
+#[cfg(not(any(feature = "esp", feature = "sim")))]
+compile_error!("Enable either the `esp` feature (the default) or `sim`.");
+#[cfg(all(feature = "esp", feature = "sim"))]
+compile_error!("The `esp` and `sim` features are mutually exclusive.");
+
+#[cfg(feature = "esp")]
+use anyhow::Context;
+use anyhow::Result;
+use bus::Bus;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use embedded_hal::pwm::SetDutyCycle;
+
+#[cfg(feature = "esp")]
 use esp_idf_hal::{
     gpio::{InputPin, OutputPin, PinDriver},
     ledc::{config::TimerConfig, *},
     prelude::*,
 };
+#[cfg(feature = "esp")]
 use esp_idf_svc::{
     sntp::{EspSntp, SntpConf},
     wifi::ClientConfiguration,
 };
 
-use max7219::MAX7219;
+use logic::rules::FlashStyle;
+#[cfg(feature = "esp")]
+use matrix::max7219::MAX7219;
+mod animations;
 mod buttons;
 mod clock;
 mod config;
+mod dimmer;
 mod event;
+mod faces;
+mod hal;
 mod lamp;
 mod leds;
+mod message;
 mod pins;
-mod screen;
+#[cfg(feature = "esp")]
 mod secrets;
+#[cfg(feature = "sim")]
+mod sim;
+mod status;
+#[cfg(feature = "esp")]
 mod wifi;
 
+#[cfg(feature = "esp")]
+use crate::wifi::*;
 use crate::{
     buttons::Buttons,
     clock::screen_loop,
     config::config_loop,
-    screen::{ScreenBuilder, ScreenConfig, Segment},
+    config::{ConfigHandler, HistoryHandler},
+    dimmer::SystemClock,
+    event::Event,
+    lamp::Lamp,
+    leds::Leds,
 };
-use crate::{config::ConfigHandler, lamp::Lamp, wifi::*};
-use crate::{event::Event, leds::Leds};
+use matrix::layout::{ScreenConfig, Segment};
+use matrix::max7219::connectors::Connector;
+use matrix::screen::Screen;
+#[cfg(feature = "esp")]
+use matrix::screen::ScreenBuilder;
 
 use std::{path::Path, thread};
 
+/// The panel: eight 8x8 displays in two rows of four, mounted upside down.
+pub fn screen_config() -> ScreenConfig {
+    let row = |posns: [u8; 4]| posns.map(Segment::inverted).to_vec();
+    ScreenConfig::new(vec![row([7, 6, 5, 4]), row([3, 2, 1, 0])]).expect("Panel layout is valid")
+}
+
+/// Start the config, screen, lamp and button tasks, then pass each event arriving on `msg_rx` on
+/// to all of them.  Anything else sending events needs its own clone of `msg_tx`.
+fn spawn_tasks<D, P, B>(
+    screen: Screen<D>,
+    leds: Leds<P>,
+    (left_button, right_button): (B, B),
+    (msg_tx, msg_rx): (Sender<Event>, Receiver<Event>),
+) -> !
+where
+    D: Connector + Send + 'static,
+    P: SetDutyCycle + Send + 'static,
+    B: embedded_hal::digital::InputPin + Send + 'static,
+{
+    let mut config_handler = ConfigHandler::new(Path::new("config.json"));
+    let config = config_handler.get();
+    let history_handler = HistoryHandler::new(Path::new("history.json"));
+
+    let (flash_tx, flash_rx) = bounded::<FlashStyle>(4);
+    // Every task gets every event: the loop at the end passes each one on to all of them.
+    let mut bus = Bus::new(8);
+
+    let _config_task = {
+        let rx = bus.add_rx();
+        thread::Builder::new()
+            .stack_size(hal::STACK_SIZE)
+            .spawn(move || config_loop(rx, &mut config_handler))
+    };
+
+    let _screen_task = {
+        let rx = bus.add_rx();
+        let config = config.clone();
+        thread::Builder::new()
+            .stack_size(hal::STACK_SIZE)
+            .spawn(move || screen_loop(screen, rx, flash_tx, config, history_handler, SystemClock))
+    };
+
+    let _lamp_task = {
+        let mut lamp = Lamp::new(leds, config.clone());
+        let rx = bus.add_rx();
+
+        thread::Builder::new()
+            .stack_size(hal::STACK_SIZE)
+            .spawn(move || lamp.run(rx, flash_rx))
+    };
+
+    let _button_task = {
+        let mut buttons = Buttons::new(left_button, right_button, config);
+
+        thread::Builder::new()
+            .stack_size(hal::STACK_SIZE)
+            .spawn(move || buttons.run(msg_tx))
+    };
+
+    // The screen loop sets the brightness from the schedule as soon as it starts.
+    loop {
+        match msg_rx.recv() {
+            Ok(msg) => {
+                log::info!("Broadcast message {msg:?}");
+                bus.broadcast(msg);
+            }
+            Err(e) => log::error!("Error receiving message: {e:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "sim")]
+fn main() -> Result<!> {
+    sim::run()
+}
+
+#[cfg(feature = "esp")]
 fn main() -> Result<!> {
     // It is necessary to call this function once. Otherwise some patches to the runtime
     // implemented by esp-idf-sys might not link properly. See https://github.com/esp-rs/esp-idf-template/issues/71
@@ -44,9 +152,6 @@ fn main() -> Result<!> {
     // Bind the log crate to the ESP Logging facilities
     esp_idf_svc::log::EspLogger::initialize_default();
 
-    let mut config_handler = ConfigHandler::new(Path::new("config.json"));
-    let config = config_handler.get();
-
     let peripherals = Peripherals::take()?;
     let data = PinDriver::output(peripherals.pins.gpio26.downgrade_output())?;
     let cs = PinDriver::output(peripherals.pins.gpio33.downgrade_output())?;
@@ -55,48 +160,15 @@ fn main() -> Result<!> {
     let _buzz = PinDriver::output(peripherals.pins.gpio27.downgrade_output())?;
 
     let screen = {
-        let segments = vec![
-            Segment::inverted(7),
-            Segment::inverted(6),
-            Segment::inverted(5),
-            Segment::inverted(4),
-            Segment::inverted(3),
-            Segment::inverted(2),
-            Segment::inverted(1),
-            Segment::inverted(0),
-        ];
-        let config = ScreenConfig {
-            n_displays: 8,
-            cols: 4 * 8,
-            rows: 2 * 8,
-            segments,
-            row_length: 4,
-        };
         let raw_display = MAX7219::from_pins(8, data, cs, clk)
             .ok() // hack for non convertable error types.
             .context("Failed to get display")?;
-        ScreenBuilder::new(config).build(raw_display)?
+        ScreenBuilder::new(screen_config()).build(raw_display)?
     };
 
     let (msg_tx, msg_rx) = bounded::<Event>(8);
 
-    let _config_task = {
-        let rx = msg_rx.clone();
-        thread::Builder::new()
-            .stack_size(4096)
-            .spawn(move || config_loop(rx, &mut config_handler))
-    };
-
-    let _screen_task = {
-        let rx = msg_rx.clone();
-        let tx = msg_tx.clone();
-        let config = config.clone();
-        thread::Builder::new()
-            .stack_size(4096)
-            .spawn(move || screen_loop(screen, rx, tx, config))
-    };
-
-    let _lamp_task = {
+    let leds = {
         let timer_driver = LedcTimerDriver::new(
             peripherals.ledc.timer0,
             &TimerConfig::default().frequency(25.kHz().into()),
@@ -117,13 +189,7 @@ fn main() -> Result<!> {
             peripherals.pins.gpio21,
         )?;
 
-        let leds = Leds::new(red, green, blue);
-        let mut lamp = Lamp::new(leds, config.clone());
-        let rx = msg_rx.clone();
-
-        thread::Builder::new()
-            .stack_size(4096)
-            .spawn(move || lamp.run(rx))
+        Leds::new(red, green, blue)
     };
 
     let _wifi_task = {
@@ -140,11 +206,10 @@ fn main() -> Result<!> {
             .build()
             .context("Failed to setup wifi")?;
 
-        let rx = msg_rx.clone();
         let tx = msg_tx.clone();
         thread::Builder::new()
-            .stack_size(4096)
-            .spawn(move || wifi_loop(wifi, rx, tx))
+            .stack_size(hal::STACK_SIZE)
+            .spawn(move || wifi_loop(wifi, tx))
     };
 
     let tx = msg_tx.clone();
@@ -152,27 +217,11 @@ fn main() -> Result<!> {
         let _ = tx.try_send(Event::ClockSynced);
     });
 
-    let _button_task = {
-        let left_button = PinDriver::input(peripherals.pins.gpio34.downgrade_input())?;
-        let right_button = PinDriver::input(peripherals.pins.gpio35.downgrade_input())?;
-        let mut buttons = Buttons::new(left_button, right_button, config.clone());
-        let tx = msg_tx.clone();
-
-        thread::Builder::new()
-            .stack_size(4096)
-            .spawn(move || buttons.run(tx))
-    };
-
-    // Send startup messages
-    let _ = msg_tx.send(Event::ChangeBrightness(0));
-    let _ = msg_tx.send(Event::ChangeBrightness(1));
+    let buttons = (
+        PinDriver::input(peripherals.pins.gpio34.downgrade_input())?,
+        PinDriver::input(peripherals.pins.gpio35.downgrade_input())?,
+    );
 
     log::info!("Booted");
-    loop {
-        log::info!("Waiting for next message");
-        match msg_rx.recv() {
-            Ok(msg) => log::info!("Broadcast message {msg:?}"),
-            Err(e) => log::error!("Error receiving message: {e:?}"),
-        }
-    }
+    spawn_tasks(screen, leds, buttons, (msg_tx, msg_rx))
 }
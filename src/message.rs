@@ -0,0 +1,220 @@
+//! Arbitrary text on the screen, for [`Event::ShowStatic`](crate::event::Event::ShowStatic).
+//!
+//! Messages which fit are centred and held for a while; longer ones scroll past from right to
+//! left, as a marquee.  Either way the clock comes back when they're done.
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use matrix::{max7219::connectors::Connector, screen::Screen};
+use serde::{Deserialize, Serialize};
+use u8g2_fonts::{
+    fonts,
+    types::{FontColor, VerticalPosition},
+    FontRenderer,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageFont {
+    /// 4px high, as used for the seconds.
+    Tiny,
+    /// 7px high, as used for hours and minutes.
+    #[default]
+    Small,
+    /// 10px high.
+    Large,
+}
+
+impl MessageFont {
+    fn renderer(&self) -> FontRenderer {
+        match self {
+            MessageFont::Tiny => FontRenderer::new::<fonts::u8g2_font_u8glib_4_tf>(),
+            MessageFont::Small => FontRenderer::new::<fonts::u8g2_font_5x7_tf>(),
+            MessageFont::Large => FontRenderer::new::<fonts::u8g2_font_6x10_tf>(),
+        }
+    }
+}
+
+/// How messages are shown.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageConfig {
+    pub font: MessageFont,
+    /// Scrolling speed of long messages, in pixels per second.
+    pub speed: u32,
+    /// How many times long messages scroll past.
+    pub repeats: u32,
+    /// How long short messages stay up, in milliseconds.
+    pub hold_ms: u32,
+}
+
+impl Default for MessageConfig {
+    fn default() -> MessageConfig {
+        MessageConfig {
+            font: MessageFont::default(),
+            speed: 20,
+            repeats: 1,
+            hold_ms: 3000,
+        }
+    }
+}
+
+/// Where a message goes over time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Marquee {
+    /// Short enough to fit: centred at `x` until `hold` is up.
+    Centred { x: i32, hold: Duration },
+    /// Too long: entering from the right edge and leaving off the left, `repeats` times.
+    Scrolling {
+        screen_width: u32,
+        width: u32,
+        speed: u32,
+        repeats: u32,
+    },
+}
+
+impl Marquee {
+    pub fn new(width: u32, screen_width: u32, config: &MessageConfig) -> Marquee {
+        if width <= screen_width {
+            Marquee::Centred {
+                x: ((screen_width - width) / 2) as i32,
+                hold: Duration::from_millis(config.hold_ms.into()),
+            }
+        } else {
+            Marquee::Scrolling {
+                screen_width,
+                width,
+                speed: config.speed.max(1),
+                repeats: config.repeats.max(1),
+            }
+        }
+    }
+
+    /// The left edge of the text, `elapsed` after it first appeared, or `None` once it's done.
+    pub fn x(&self, elapsed: Duration) -> Option<i32> {
+        match *self {
+            Marquee::Centred { x, hold } => (elapsed < hold).then_some(x),
+            Marquee::Scrolling {
+                screen_width,
+                width,
+                speed,
+                repeats,
+            } => {
+                let pass = (screen_width + width) as u128;
+                let moved = elapsed.as_millis() * speed as u128 / 1000;
+                (moved < pass * repeats as u128)
+                    .then(|| screen_width as i32 - (moved % pass) as i32)
+            }
+        }
+    }
+
+    /// How long to wait before the next frame: one pixel's travel while scrolling.
+    pub fn frame_time(&self) -> Duration {
+        match *self {
+            Marquee::Centred { .. } => Duration::from_millis(100),
+            Marquee::Scrolling { speed, .. } => {
+                Duration::from_millis((1000 / speed as u64).clamp(10, 100))
+            }
+        }
+    }
+}
+
+/// A message being shown.
+pub struct Message {
+    text: String,
+    font: FontRenderer,
+    marquee: Marquee,
+    started: Instant,
+}
+
+impl Message {
+    /// Start showing `text` on a screen `screen_width` pixels wide.
+    pub fn new(text: String, screen_width: u32, config: &MessageConfig) -> Result<Message> {
+        let font = config.font.renderer();
+        let dimensions = font
+            .get_rendered_dimensions(text.as_str(), Point::zero(), VerticalPosition::Center)
+            .map_err(|e| anyhow::anyhow!("Can't render '{text}': {e:?}"))?;
+        let width = dimensions.advance.x.max(0) as u32;
+        Ok(Message {
+            text,
+            font,
+            marquee: Marquee::new(width, screen_width, config),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn frame_time(&self) -> Duration {
+        self.marquee.frame_time()
+    }
+
+    /// Draw the current frame, ready to flush, returning false once the message is over.
+    pub fn show<T>(&self, screen: &mut Screen<T>) -> Result<bool>
+    where
+        T: Connector,
+    {
+        let Some(x) = self.marquee.x(self.started.elapsed()) else {
+            return Ok(false);
+        };
+        screen.clear();
+        self.font
+            .render(
+                self.text.as_str(),
+                Point::new(x, screen.bounding_box().center().y),
+                VerticalPosition::Center,
+                FontColor::Transparent(BinaryColor::On),
+                screen,
+            )
+            .map_err(|e| anyhow::anyhow!("Can't render '{}': {e:?}", self.text))?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::message::{Marquee, MessageConfig};
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn short_messages_are_centred_then_cleared() {
+        let marquee = Marquee::new(12, 32, &MessageConfig::default());
+        assert_eq!(marquee.x(ms(0)), Some(10));
+        assert_eq!(marquee.x(ms(2999)), Some(10));
+        assert_eq!(marquee.x(ms(3000)), None);
+    }
+
+    #[test]
+    fn long_messages_scroll_across_and_off() {
+        let config = MessageConfig {
+            speed: 10,
+            ..MessageConfig::default()
+        };
+        // 40 + 32 pixels to travel, at 10 per second.
+        let marquee = Marquee::new(40, 32, &config);
+        assert_eq!(marquee.x(ms(0)), Some(32));
+        assert_eq!(marquee.x(ms(1000)), Some(22));
+        assert_eq!(marquee.x(ms(7100)), Some(-39));
+        assert_eq!(marquee.x(ms(7200)), None);
+        assert_eq!(marquee.frame_time(), ms(100));
+    }
+
+    #[test]
+    fn repeats_start_again_from_the_right() {
+        let config = MessageConfig {
+            speed: 100,
+            repeats: 3,
+            ..MessageConfig::default()
+        };
+        let marquee = Marquee::new(68, 32, &config);
+        assert_eq!(marquee.x(ms(999)), Some(-67));
+        assert_eq!(marquee.x(ms(1000)), Some(32));
+        assert_eq!(marquee.x(ms(2999)), Some(-67));
+        assert_eq!(marquee.x(ms(3000)), None);
+        assert_eq!(marquee.frame_time(), ms(10));
+    }
+}
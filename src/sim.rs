@@ -0,0 +1,220 @@
+//! A stand-in for the clock's hardware, so the app can run on the host with the `sim` feature.
+//!
+//! The display is driven through fake pins which decode the MAX7219 bitstream ([`matrix::mock`]),
+//! the lamp through fake PWM channels, and the buttons are pressed on a script given on the
+//! command line:
+//!
+//! ```sh
+//! cargo run --no-default-features --features sim -- right@2 right@2.5 left@5:1
+//! ```
+//!
+//! presses the right button 2s and 2.5s after starting, then holds the left one for a second at
+//! 5s.  The matrix and the lamp colour are drawn to the terminal; logs go to stderr.
+use std::{
+    convert::Infallible,
+    env,
+    io::{self, Write},
+    ops::Range,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use crossbeam_channel::bounded;
+use embedded_hal::{digital::InputPin, pwm::SetDutyCycle};
+use matrix::{
+    layout::ScreenConfig,
+    mock::{self, register, Chain},
+    screen::ScreenBuilder,
+};
+use rgb::RGB8;
+
+use crate::{leds::Leds, screen_config, spawn_tasks};
+
+pub struct Delay;
+
+impl Delay {
+    pub fn new_default() -> Delay {
+        Delay
+    }
+
+    pub fn delay_ms(&self, ms: u32) {
+        thread::sleep(Duration::from_millis(ms.into()));
+    }
+}
+
+pub struct Ets;
+
+impl Ets {
+    pub fn delay_ms(ms: u32) {
+        thread::sleep(Duration::from_millis(ms.into()));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Channel {
+    Red,
+    Green,
+    Blue,
+}
+
+/// One colour channel of the lamp.
+pub struct SimPwm {
+    lamp: Arc<Mutex<RGB8>>,
+    channel: Channel,
+}
+
+impl embedded_hal::pwm::ErrorType for SimPwm {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for SimPwm {
+    fn get_max_duty_cycle(&self) -> u16 {
+        u8::MAX.into()
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        let level = duty.min(u8::MAX.into()) as u8;
+        let mut lamp = self.lamp.lock().unwrap();
+        match self.channel {
+            Channel::Red => lamp.r = level,
+            Channel::Green => lamp.g = level,
+            Channel::Blue => lamp.b = level,
+        }
+        Ok(())
+    }
+}
+
+/// A button held down during scripted intervals after the simulator starts.
+pub struct ScriptedPin {
+    start: Instant,
+    presses: Vec<Range<Duration>>,
+}
+
+impl embedded_hal::digital::ErrorType for ScriptedPin {
+    type Error = Infallible;
+}
+
+impl InputPin for ScriptedPin {
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let now = self.start.elapsed();
+        Ok(self.presses.iter().any(|press| press.contains(&now)))
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
+
+/// Parse presses like `left@4.5` or `right@2:1` (pressed at 2s, held for 1s) into the left and
+/// right buttons.
+fn script(args: impl Iterator<Item = String>) -> Result<(ScriptedPin, ScriptedPin)> {
+    let start = Instant::now();
+    let mut left = ScriptedPin {
+        start,
+        presses: Vec::new(),
+    };
+    let mut right = ScriptedPin {
+        start,
+        presses: Vec::new(),
+    };
+    let seconds = |s: &str| {
+        s.parse::<f32>()
+            .ok()
+            .filter(|s| s.is_finite() && *s >= 0.)
+            .map(Duration::from_secs_f32)
+            .with_context(|| format!("Invalid time '{s}'"))
+    };
+    for arg in args {
+        let Some((button, time)) = arg.split_once('@') else {
+            bail!("Expected a press like 'left@4.5' or 'right@2:1', not '{arg}'");
+        };
+        let (at, held) = time.split_once(':').unwrap_or((time, "0.1"));
+        let at = seconds(at)?;
+        let press = at..at + seconds(held)?;
+        match button {
+            "left" => left.presses.push(press),
+            "right" => right.presses.push(press),
+            _ => bail!("Unknown button '{button}': expected 'left' or 'right'"),
+        }
+    }
+    Ok((left, right))
+}
+
+/// Draw the matrix, two characters per LED, and a swatch of the lamp's colour.
+fn render(
+    out: &mut impl Write,
+    layout: &ScreenConfig,
+    chain: &Chain,
+    lamp: RGB8,
+) -> io::Result<()> {
+    write!(out, "\x1b[H")?;
+    for y in 0..layout.rows() {
+        for x in 0..layout.cols() {
+            let (row_index, bit) = layout.position(x, y).expect("Pixel is on the screen");
+            let (device, row) = (row_index / 8, row_index % 8);
+            if chain.lit(device, row, bit) {
+                // Dim LEDs are still visible, so scale from a quarter to full red.
+                let intensity = chain.registers(device)[register::INTENSITY] as u32 & 0x0F;
+                let red = 64 + intensity * (255 - 64) / 15;
+                write!(out, "\x1b[38;2;{red};0;0m██")?;
+            } else {
+                write!(out, "\x1b[38;2;40;0;0m··")?;
+            }
+        }
+        writeln!(out, "\x1b[0m")?;
+    }
+    let RGB8 { r, g, b } = lamp;
+    writeln!(
+        out,
+        "\n\x1b[48;2;{r};{g};{b}m        \x1b[0m lamp #{r:02x}{g:02x}{b:02x}"
+    )?;
+    out.flush()
+}
+
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{} {}: {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+pub fn run() -> Result<!> {
+    let _ = log::set_logger(&LOGGER).map(|()| log::set_max_level(log::LevelFilter::Info));
+    let (left_button, right_button) = script(env::args().skip(1))?;
+
+    let layout = screen_config();
+    let (raw_display, chain) = mock::display(layout.n_displays());
+    let screen = ScreenBuilder::new(layout.clone()).build(raw_display)?;
+    let lamp_colour = Arc::new(Mutex::new(RGB8::default()));
+    let [red, green, blue] = [Channel::Red, Channel::Green, Channel::Blue].map(|channel| SimPwm {
+        lamp: lamp_colour.clone(),
+        channel,
+    });
+    let leds = Leds::new(red, green, blue);
+
+    let _render_task = thread::Builder::new().spawn(move || -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        write!(out, "\x1b[2J")?;
+        loop {
+            let colour = *lamp_colour.lock().unwrap();
+            render(&mut out, &layout, &chain.lock().unwrap(), colour)?;
+            thread::sleep(Duration::from_millis(50));
+        }
+    });
+
+    log::info!("Simulator started");
+    spawn_tasks(screen, leds, (left_button, right_button), bounded(8))
+}
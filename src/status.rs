@@ -0,0 +1,275 @@
+//! Badges in the corners of the screen for what the clock is up to: wifi top left, NTP sync top
+//! right, the alarm bottom left and significance mode bottom right.
+//!
+//! Each badge is set by an event and stays until another event clears it.
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use embedded_graphics::{
+    geometry::AnchorPoint,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use matrix::font::Badge;
+use serde::{Deserialize, Serialize};
+
+use crate::event::Event;
+
+/// Which badges are shown.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusConfig {
+    pub wifi: bool,
+    pub sync: bool,
+    pub alarm: bool,
+    /// Off by default, since the digital face already shows today's count in significance mode.
+    pub significance: bool,
+    /// How long after the last NTP sync the sync badge starts blinking, in hours.
+    pub sync_stale_hours: u32,
+}
+
+impl Default for StatusConfig {
+    fn default() -> StatusConfig {
+        StatusConfig {
+            wifi: true,
+            sync: true,
+            alarm: true,
+            significance: false,
+            sync_stale_hours: 24,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Network {
+    /// Blinks until connected.
+    Connecting,
+    Connected,
+    AccessPoint,
+}
+
+/// What the badges show, kept up to date from events.
+#[derive(Debug)]
+pub struct Status {
+    network: Option<Network>,
+    synced: Option<Instant>,
+    alarm: bool,
+    significance: bool,
+    /// Blinking is timed from here.
+    started: Instant,
+}
+
+const BLINK: Duration = Duration::from_millis(500);
+
+impl Status {
+    pub fn new(significant_mode: bool, now: Instant) -> Status {
+        Status {
+            network: None,
+            synced: None,
+            alarm: false,
+            significance: significant_mode,
+            started: now,
+        }
+    }
+
+    /// Set or clear whichever badge `event` is about, which happened at `now`.
+    pub fn update(&mut self, event: &Event, now: Instant) {
+        match event {
+            Event::NetworkConnecting => self.network = Some(Network::Connecting),
+            Event::NetworkConnected => self.network = Some(Network::Connected),
+            Event::APActivated => self.network = Some(Network::AccessPoint),
+            Event::APDisactivated => self.network = None,
+            Event::ClockSynced => self.synced = Some(now),
+            Event::AlarmArmed => self.alarm = true,
+            Event::AlarmDisarmed => self.alarm = false,
+            Event::ChangeConfig(config) => self.significance = config.significant_mode,
+            _ => (),
+        }
+    }
+
+    /// The badges to show at `now`, top left, top right, bottom left and bottom right.
+    fn badges(&self, config: &StatusConfig, now: Instant) -> [Option<Badge>; 4] {
+        // On for the first half of each blink.
+        let blink_on = now.saturating_duration_since(self.started).as_millis()
+            % (2 * BLINK.as_millis())
+            < BLINK.as_millis();
+        let stale = Duration::from_secs(config.sync_stale_hours as u64 * 60 * 60);
+        let wifi = match self.network {
+            Some(Network::Connecting) => blink_on.then_some(Badge::Wifi),
+            Some(Network::Connected) => Some(Badge::Wifi),
+            Some(Network::AccessPoint) => Some(Badge::AccessPoint),
+            None => None,
+        };
+        let sync = self.synced.and_then(|synced| {
+            let fresh = now.saturating_duration_since(synced) < stale;
+            (fresh || blink_on).then_some(Badge::Sync)
+        });
+        [
+            wifi.filter(|_| config.wifi),
+            sync.filter(|_| config.sync),
+            (config.alarm && self.alarm).then_some(Badge::Alarm),
+            (config.significance && self.significance).then_some(Badge::Star),
+        ]
+    }
+
+    /// Draw the badges due at `now` over `target`, each on a blank patch so it can be read over
+    /// the face.
+    pub fn draw<D>(&self, config: &StatusConfig, now: Instant, target: &mut D) -> Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        D::Error: Debug,
+    {
+        let bounds = target.bounding_box();
+        let far = bounds.size.saturating_sub(Size::new_equal(Badge::SIZE));
+        let corners = [
+            Point::zero(),
+            Point::new(far.width as i32, 0),
+            Point::new(0, far.height as i32),
+            Point::new(far.width as i32, far.height as i32),
+        ];
+        for (badge, corner) in self.badges(config, now).into_iter().zip(corners) {
+            let Some(badge) = badge else {
+                continue;
+            };
+            let origin = bounds.top_left + corner;
+            // With a pixel's margin, which is off the screen except on the sides facing in.
+            let patch = Rectangle::new(origin, Size::new_equal(Badge::SIZE))
+                .resized(Size::new_equal(Badge::SIZE + 2), AnchorPoint::Center);
+            patch
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+                .draw(target)
+                .map_err(|e| anyhow!("Can't clear under {badge:?}: {e:?}"))?;
+            badge
+                .draw(origin, target)
+                .map_err(|e| anyhow!("Can't draw {badge:?}: {e:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use embedded_graphics::prelude::*;
+    use matrix::{bitmap::Bitmap, font::Badge};
+
+    use crate::config::Config;
+    use crate::event::Event;
+    use crate::status::{Status, StatusConfig};
+
+    fn drawn(status: &Status, config: &StatusConfig, now: Instant) -> Bitmap {
+        let mut bitmap = Bitmap::new(32, 16).inverted();
+        status.draw(config, now, &mut bitmap).unwrap();
+        bitmap
+    }
+
+    fn badge(badge: Badge) -> Bitmap {
+        let mut bitmap = Bitmap::new(Badge::SIZE, Badge::SIZE);
+        badge.draw(Point::zero(), &mut bitmap).unwrap();
+        bitmap
+    }
+
+    #[test]
+    fn badges_persist_until_cleared() {
+        let start = Instant::now();
+        let mut status = Status::new(false, start);
+        let config = StatusConfig::default();
+        assert_eq!(status.badges(&config, start), [None; 4]);
+
+        status.update(&Event::NetworkConnected, start);
+        status.update(&Event::AlarmArmed, start);
+        let later = start + Duration::from_secs(600);
+        assert_eq!(
+            status.badges(&config, later),
+            [Some(Badge::Wifi), None, Some(Badge::Alarm), None]
+        );
+
+        status.update(&Event::APActivated, later);
+        status.update(&Event::AlarmDisarmed, later);
+        assert_eq!(
+            status.badges(&config, later),
+            [Some(Badge::AccessPoint), None, None, None]
+        );
+        status.update(&Event::APDisactivated, later);
+        assert_eq!(status.badges(&config, later), [None; 4]);
+    }
+
+    #[test]
+    fn connecting_blinks() {
+        let start = Instant::now();
+        let mut status = Status::new(false, start);
+        status.update(&Event::NetworkConnecting, start);
+        let config = StatusConfig::default();
+        let wifi = |ms| status.badges(&config, start + Duration::from_millis(ms))[0];
+        assert_eq!(wifi(0), Some(Badge::Wifi));
+        assert_eq!(wifi(600), None);
+        assert_eq!(wifi(1100), Some(Badge::Wifi));
+    }
+
+    #[test]
+    fn sync_blinks_once_stale() {
+        let start = Instant::now();
+        let mut status = Status::new(false, start);
+        status.update(&Event::ClockSynced, start);
+        let config = StatusConfig {
+            sync_stale_hours: 1,
+            ..StatusConfig::default()
+        };
+        let sync = |s, ms| {
+            status.badges(
+                &config,
+                start + Duration::from_secs(s) + Duration::from_millis(ms),
+            )[1]
+        };
+        assert_eq!(sync(3599, 600), Some(Badge::Sync));
+        assert_eq!(sync(3600, 0), Some(Badge::Sync));
+        assert_eq!(sync(3600, 600), None);
+    }
+
+    #[test]
+    fn significance_follows_the_config() {
+        let start = Instant::now();
+        let mut status = Status::new(true, start);
+        let config = StatusConfig {
+            significance: true,
+            ..StatusConfig::default()
+        };
+        assert_eq!(status.badges(&config, start)[3], Some(Badge::Star));
+        let off = Config {
+            significant_mode: false,
+            ..Config::default()
+        };
+        status.update(&Event::ChangeConfig(off), start);
+        assert_eq!(status.badges(&config, start)[3], None);
+    }
+
+    #[test]
+    fn badges_sit_in_their_corners_on_a_blank_patch() {
+        let start = Instant::now();
+        let mut status = Status::new(true, start);
+        status.update(&Event::NetworkConnected, start);
+        status.update(&Event::AlarmArmed, start);
+        let config = StatusConfig {
+            significance: true,
+            ..StatusConfig::default()
+        };
+        let bitmap = drawn(&status, &config, start);
+        let size = Size::new_equal(Badge::SIZE);
+        assert_eq!(bitmap.crop(Point::zero(), size), badge(Badge::Wifi));
+        assert_eq!(bitmap.crop(Point::new(0, 12), size), badge(Badge::Alarm));
+        assert_eq!(bitmap.crop(Point::new(28, 12), size), badge(Badge::Star));
+        // The margin around the badge is cleared, but nothing beyond it.
+        assert!(!bitmap.get(4, 4) && !bitmap.get(4, 0) && !bitmap.get(0, 4));
+        assert!(bitmap.get(5, 5) && bitmap.get(5, 0));
+        // Nothing's synced, so the top right is untouched.
+        assert_eq!(
+            bitmap.crop(Point::new(27, 0), Size::new(5, 5)),
+            Bitmap::new(5, 5).inverted()
+        );
+    }
+}
@@ -1,6 +1,5 @@
 use anyhow::Context;
 use anyhow::Result;
-use crossbeam_channel::Receiver;
 use crossbeam_channel::Sender;
 use embedded_svc::wifi::{AccessPointConfiguration, Configuration};
 use esp_idf_hal::delay::Delay;
@@ -112,7 +111,8 @@ impl Wifi {
     }
 }
 
-pub fn wifi_loop(mut wifi: Wifi, _rx: Receiver<Event>, tx: Sender<Event>) -> ! {
+pub fn wifi_loop(mut wifi: Wifi, tx: Sender<Event>) -> ! {
+    let _ = tx.send(Event::NetworkConnecting);
     if let Ok(()) = wifi.try_connect(10) {
         tx.send(Event::NetworkConnected)
             .expect("TX channel disconnected!");